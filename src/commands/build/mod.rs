@@ -2,9 +2,16 @@ pub use crate::commands::build::postgres::Postgres;
 pub use crate::commands::build::shapefile::Shapefile;
 pub use crate::commands::build::sqlite::SQLite;
 use crate::repo::Walk;
+use crate::utils::ResultExit;
+use crate::wof::WOFGeoJSON;
+use crate::JsonValue;
 use clap::Parser;
 use log::{error, info};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Condvar, Mutex};
+use std::thread;
 use std::time::SystemTime;
 
 mod postgres;
@@ -34,14 +41,123 @@ impl Build {
   }
 }
 
-pub fn build_database<F: FnMut(Option<String>, Option<PathBuf>) -> Result<(), String>>(
-  directories: &Vec<String>,
+/// Number of documents added between two commits of the destination.
+const BATCH_SIZE: u64 = 10_000;
+
+/// Where documents are written during a build, a single thread adds them in the order they are
+/// read. Documents are added between `begin` and `commit`, in batches of `BATCH_SIZE`.
+pub trait Destination {
+  fn add(&mut self, document: WOFGeoJSON) -> Result<(), String>;
+
+  fn begin(&mut self) -> Result<(), String> {
+    Ok(())
+  }
+
+  fn commit(&mut self) -> Result<(), String> {
+    Ok(())
+  }
+}
+
+/// Number of parsing threads when `--threads` is not set.
+pub fn default_threads() -> usize {
+  thread::available_parallelism()
+    .map(|threads| threads.get())
+    .unwrap_or(1)
+}
+
+enum Source {
+  Stdin(String),
+  File(PathBuf),
+}
+
+/// A parsed and validated document, `index` is its position in the input.
+struct Entry {
+  index: u64,
+  directory: Option<usize>,
+  path: Option<PathBuf>,
+  document: Result<JsonValue, String>,
+}
+
+/// Bounds how far the reader runs ahead of the writer, documents parsed after a slow one wait in
+/// the reorder buffer of `write_entries` and it holds at most `size` of them.
+struct Window {
+  written: Mutex<u64>,
+  advanced: Condvar,
+  size: u64,
+}
+
+impl Window {
+  fn wait_for(&self, index: u64) {
+    let written = self.written.lock().unwrap();
+    let _written = self
+      .advanced
+      .wait_while(written, |written| index >= *written + self.size)
+      .unwrap();
+  }
+
+  fn advance(&self, written: u64) {
+    *self.written.lock().unwrap() = written;
+    self.advanced.notify_all();
+  }
+}
+
+/// Documents from stdin or from the directories are parsed by `threads` workers and added by the
+/// current thread. They are added and errors are reported in the input order, whatever the number
+/// of threads.
+pub fn build_database<D: Destination>(
+  directories: &[String],
+  threads: usize,
   timings: bool,
-  add: &mut F,
+  destination: &mut D,
 ) {
-  let mut count = 0u64;
+  let threads = threads.max(1);
   let import_start = SystemTime::now();
+  let (source_sender, source_receiver) = sync_channel(threads * 16);
+  let (entry_sender, entry_receiver) = sync_channel(threads * 16);
+  let source_receiver = Mutex::new(source_receiver);
+  let window = Window {
+    written: Mutex::new(0),
+    advanced: Condvar::new(),
+    size: threads as u64 * 64,
+  };
+  let window = &window;
 
+  let count = thread::scope(|scope| {
+    scope.spawn(move || read_sources(directories, window, source_sender));
+    for _ in 0..threads {
+      let source_receiver = &source_receiver;
+      let entry_sender = entry_sender.clone();
+      scope.spawn(move || loop {
+        let next = source_receiver.lock().unwrap().recv();
+        let Ok((index, directory, source)) = next else {
+          break;
+        };
+        if entry_sender.send(parse(index, directory, source)).is_err() {
+          break;
+        }
+      });
+    }
+    drop(entry_sender);
+    write_entries(entry_receiver, window, directories, timings, destination)
+  });
+
+  if timings {
+    info!(
+      "Imported {} documents successfully in {:?}.",
+      count,
+      import_start.elapsed().unwrap()
+    );
+  } else {
+    info!("Imported {} documents successfully.", count);
+  }
+}
+
+fn read_sources(
+  directories: &[String],
+  window: &Window,
+  sources: SyncSender<(u64, Option<usize>, Source)>,
+) {
+  let mut index = 0u64;
   if crate::commands::input_pipe() {
     info!("Start import from stdin.");
     loop {
@@ -49,46 +165,114 @@ pub fn build_database<F: FnMut(Option<String>, Option<PathBuf>) -> Result<(), St
       match std::io::stdin().read_line(&mut buffer) {
         Ok(0) => break,
         Ok(_) => {
-          if let Err(e) = add(Some(buffer), None) {
-            error!("Something goes wrong with an entry from stdin: {}", e);
-          } else {
-            count = count + 1;
+          window.wait_for(index);
+          if sources.send((index, None, Source::Stdin(buffer))).is_err() {
+            return;
           }
+          index += 1;
         }
         Err(_) => break,
       }
     }
   } else {
-    for directory in directories {
-      info!("Start import for directory `{}`", directory);
-      let start = SystemTime::now();
+    for (position, directory) in directories.iter().enumerate() {
       for entry in Walk::new(directory.to_string(), false, true) {
         if let Ok(path) = entry {
-          let p = path.path();
-          if let Err(e) = add(None, Some(p.to_path_buf())) {
-            error!("Something goes wrong for {}: {}", p.display(), e);
-          } else {
-            count = count + 1;
+          let source = Source::File(path.path().to_path_buf());
+          window.wait_for(index);
+          if sources.send((index, Some(position), source)).is_err() {
+            return;
           }
+          index += 1;
         }
       }
-      if timings {
-        info!(
-          "Import for `{}` took {:?}.",
-          directory,
-          start.elapsed().unwrap()
-        );
+    }
+  }
+}
+
+fn parse(index: u64, directory: Option<usize>, source: Source) -> Entry {
+  let (path, json) = match source {
+    Source::Stdin(buffer) => (None, crate::parse_string_to_json(&buffer)),
+    Source::File(path) => {
+      let json = crate::parse_file_to_json(path.clone());
+      (Some(path), json)
+    }
+  };
+  let document = json.and_then(|json| {
+    WOFGeoJSON::as_valid_wof_geojson(&json)?;
+    Ok(json)
+  });
+  Entry {
+    index,
+    directory,
+    path,
+    document,
+  }
+}
+
+fn write_entries<D: Destination>(
+  entries: Receiver<Entry>,
+  window: &Window,
+  directories: &[String],
+  timings: bool,
+  destination: &mut D,
+) -> u64 {
+  let mut count = 0u64;
+  let mut next = 0u64;
+  let mut pending = BTreeMap::new();
+  let mut current: Option<(usize, SystemTime)> = None;
+  destination.begin().expect_exit("Can't start a transaction");
+
+  for entry in entries {
+    pending.insert(entry.index, entry);
+    while let Some(entry) = pending.remove(&next) {
+      next += 1;
+      if entry.directory != current.map(|(position, _)| position) {
+        if let Some((position, start)) = current {
+          log_directory_end(&directories[position], start, timings);
+        }
+        current = entry.directory.map(|position| {
+          info!("Start import for directory `{}`", directories[position]);
+          (position, SystemTime::now())
+        });
+      }
+      let result = entry
+        .document
+        .and_then(|json| destination.add(WOFGeoJSON::as_valid_wof_geojson(&json)?));
+      match (result, entry.path) {
+        (Ok(()), _) => {
+          count += 1;
+          // `is_multiple_of` needs Rust 1.87.
+          #[allow(clippy::manual_is_multiple_of)]
+          if count % BATCH_SIZE == 0 {
+            destination
+              .commit()
+              .expect_exit("Can't commit a transaction");
+            destination.begin().expect_exit("Can't start a transaction");
+          }
+        }
+        (Err(e), Some(path)) => error!("Something goes wrong for {}: {}", path.display(), e),
+        (Err(e), None) => error!("Something goes wrong with an entry from stdin: {}", e),
       }
     }
+    window.advance(next);
+  }
+
+  if let Some((position, start)) = current {
+    log_directory_end(&directories[position], start, timings);
   }
+  destination
+    .commit()
+    .expect_exit("Can't commit a transaction");
+  count
+}
 
+fn log_directory_end(directory: &str, start: SystemTime, timings: bool) {
   if timings {
     info!(
-      "Imported {} documents successfully in {:?}.",
-      count,
-      import_start.elapsed().unwrap()
+      "Import for `{}` took {:?}.",
+      directory,
+      start.elapsed().unwrap()
     );
-  } else {
-    info!("Imported {} documents successfully.", count);
   }
 }
@@ -1,5 +1,7 @@
+use crate::commands::build::{build_database, default_threads, Destination};
 use crate::postgres;
 use crate::utils::ResultExit;
+use crate::wof::WOFGeoJSON;
 use clap::Parser;
 use log::info;
 
@@ -49,6 +51,9 @@ pub struct Postgres {
   /// Don't insert deprecated features.
   #[arg(long = "no-deprecated")]
   pub no_deprecated: bool,
+  /// Number of threads parsing documents, the number of CPUs by default.
+  #[arg(long = "threads")]
+  pub threads: Option<usize>,
   /// Display timings during the build process, implies verbose.
   #[arg(long = "timings")]
   pub timings: bool,
@@ -78,14 +83,17 @@ impl Postgres {
     info!("Creating tables and indexes.");
     postgres.create_tables().expect_exit("Can't create tables");
 
-    crate::commands::build::build_database(&self.directories, self.timings, &mut |buffer, file| {
-      if let Some(buffer) = buffer {
-        postgres.add_string(buffer)
-      } else if let Some(file) = file {
-        postgres.add_file(file)
-      } else {
-        Ok(())
-      }
-    });
+    build_database(
+      &self.directories,
+      self.threads.unwrap_or_else(default_threads),
+      self.timings,
+      &mut postgres,
+    );
+  }
+}
+
+impl Destination for postgres::Postgres {
+  fn add(&mut self, document: WOFGeoJSON) -> Result<(), String> {
+    postgres::Postgres::add(self, document)
   }
 }
@@ -1,11 +1,10 @@
+use crate::commands::build::{build_database, default_threads, Destination};
 use crate::shapefile;
 use crate::utils::ResultExit;
 use crate::wof::WOFGeoJSON;
-use crate::JsonValue;
 use clap::builder::PossibleValuesParser;
 use clap::Parser;
 use log::{error, info};
-use std::path::Path;
 
 #[derive(Debug, Parser)]
 pub struct Shapefile {
@@ -40,6 +39,9 @@ pub struct Shapefile {
       ignore_case = false,
       default_value = "POLYGON")]
   pub shapetype: String,
+  /// Number of threads parsing documents, the number of CPUs by default.
+  #[arg(long = "threads")]
+  pub threads: Option<usize>,
   /// Activate verbose mode.
   #[arg(short = 'v', long = "verbose")]
   pub verbose: bool,
@@ -63,7 +65,7 @@ impl Shapefile {
       }
     };
 
-    let mut shapefile = shapefile::Shapefile::new(
+    let shapefile = shapefile::Shapefile::new(
       &self.out,
       shapefile::ShapefileOpts {
         deprecated: false,
@@ -74,50 +76,49 @@ impl Shapefile {
 
     info!("Create a shapefile with {:?}", shapetype);
 
-    crate::commands::build::build_database(&self.directories, self.timings, &mut |buffer, file| {
-      if let Some(buffer) = buffer {
-        self.add_string(&mut shapefile, buffer)
-      } else if let Some(file) = file {
-        self.add_file(&mut shapefile, file)
-      } else {
-        Ok(())
-      }
-    });
-  }
-
-  fn add_file<P: AsRef<Path>>(
-    &self,
-    shapefile: &mut shapefile::Shapefile,
-    path: P,
-  ) -> Result<(), String> {
-    let json = crate::parse_file_to_json(path.as_ref().to_path_buf())?;
-    self.add_json(shapefile, json)
+    build_database(
+      &self.directories,
+      self.threads.unwrap_or_else(default_threads),
+      self.timings,
+      &mut FilteredShapefile {
+        command: self,
+        shapefile,
+      },
+    );
   }
+}
 
-  fn add_string(&self, shapefile: &mut shapefile::Shapefile, string: String) -> Result<(), String> {
-    let json = crate::parse_string_to_json(&string)?;
-    self.add_json(shapefile, json)
-  }
+/// Shapefile destination keeping only the documents selected by the command.
+struct FilteredShapefile<'a> {
+  command: &'a Shapefile,
+  shapefile: shapefile::Shapefile,
+}
 
-  fn add_json(&self, shapefile: &mut shapefile::Shapefile, json: JsonValue) -> Result<(), String> {
-    let geojson = WOFGeoJSON::as_valid_wof_geojson(&json)?;
-    if let Some(include) = &self.include {
-      if !include.contains(&geojson.get_placetype()) {
-        return Ok(());
-      }
+impl Destination for FilteredShapefile<'_> {
+  fn add(&mut self, geojson: WOFGeoJSON) -> Result<(), String> {
+    let placetype = geojson.get_placetype();
+    let command = self.command;
+    if command
+      .include
+      .as_ref()
+      .is_some_and(|include| !include.contains(&placetype))
+    {
+      return Ok(());
     }
-    if let Some(exclude) = &self.exclude {
-      if exclude.contains(&geojson.get_placetype()) {
-        return Ok(());
-      }
+    if command
+      .exclude
+      .as_ref()
+      .is_some_and(|exclude| exclude.contains(&placetype))
+    {
+      return Ok(());
     }
-    if let Some(belongs_to) = &self.belongs_to {
+    if let Some(belongs_to) = &command.belongs_to {
       for id in &geojson.get_belongs_to() {
         if !belongs_to.contains(id) {
           return Ok(());
         }
       }
     }
-    shapefile.add(geojson)
+    self.shapefile.add(geojson)
   }
 }
@@ -1,10 +1,15 @@
 use crate::commands::assert_directory_exists;
+use crate::commands::build::{build_database, default_threads, Destination};
 use crate::sqlite;
+use crate::std::StringifyError;
 use crate::utils::ResultExit;
+use crate::wof::WOFGeoJSON;
 use clap::builder::PossibleValuesParser;
 use clap::Parser;
-use log::info;
-use std::path::Path;
+use log::{error, info, warn};
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Parser)]
 pub struct SQLite {
@@ -23,6 +28,27 @@ pub struct SQLite {
   /// Preset for pelias use. Will insert only in geojson and spr tables.
   #[arg(long = "preset", value_parser = PossibleValuesParser::new(&["pelias"]))]
   pub preset: Option<String>,
+  /// Fill the full text search table used by `wof search`.
+  #[arg(long = "search")]
+  pub search: bool,
+  /// Fill the rtree table with the bbox of each polygon.
+  #[arg(long = "rtree")]
+  pub rtree: bool,
+  /// Fill the properties table, documents without their geometry.
+  #[arg(long = "properties")]
+  pub properties: bool,
+  /// Fill the geometries table, geometries without the properties of their document.
+  #[arg(long = "geometries")]
+  pub geometries: bool,
+  /// Update an existing database with the documents changed since a commit, instead of a full build.
+  #[arg(long = "update", requires = "since")]
+  pub update: bool,
+  /// Commit or branch of the last build, used with `--update`.
+  #[arg(long = "since", requires = "update")]
+  pub since: Option<String>,
+  /// Number of threads parsing documents, the number of CPUs by default.
+  #[arg(long = "threads")]
+  pub threads: Option<usize>,
   /// Display timings during the build process, implies verbose.
   #[arg(long = "timings")]
   pub timings: bool,
@@ -50,31 +76,350 @@ impl SQLite {
       false
     };
 
-    info!("Creating database: `{}`", out_path.as_path().display());
+    let temporary_path = temporary_path(&out_path);
+    if temporary_path.exists() {
+      std::fs::remove_file(&temporary_path).expect_exit("Can't remove the temporary database");
+    }
+    let mut opts = sqlite::SQLiteOpts {
+      pretty: !self.no_pretty,
+      deprecated: !self.no_deprecated,
+      names: !pelias_preset,
+      ancestors: !pelias_preset,
+      concordances: !pelias_preset,
+      search: self.search,
+      rtree: self.rtree,
+      properties: self.properties,
+      geometries: self.geometries,
+      ..Default::default()
+    };
+    if self.update {
+      if !out_path.exists() {
+        error!(
+          "Can't update `{}`, the database does not exist",
+          out_path.display()
+        );
+        std::process::exit(1);
+      }
+      info!(
+        "Copying `{}` to `{}`",
+        out_path.display(),
+        temporary_path.display()
+      );
+      sqlite::SQLite::new(&out_path, sqlite::SQLiteOpts::default())
+        .and_then(|original| original.copy_to(&temporary_path))
+        .expect_exit("Can't copy the database");
+      opts = existing_tables(&temporary_path, opts).expect_exit("Can't read the database tables");
+    }
+
+    info!("Creating database: `{}`", temporary_path.display());
+    let mut sqlite =
+      sqlite::SQLite::new(&temporary_path, opts).expect_exit("Can't open the database");
+
+    info!("Creating tables and indexes.");
+    sqlite.create_tables().expect_exit("Can't create tables");
+
+    if let Some(since) = &self.since {
+      self.update(&sqlite, since);
+    } else {
+      build_database(
+        &self.directories,
+        self.threads.unwrap_or_else(default_threads),
+        self.timings,
+        &mut sqlite,
+      );
+    }
+    drop(sqlite);
+
+    replace_database(&temporary_path, &out_path).expect_exit("Can't replace the database");
+    info!("Database written to `{}`", out_path.display());
+  }
+
+  /// Every document with a changed file is deleted then added again from its folder. Documents
+  /// without a file left are only deleted. Alternate geometries are left out, like in a full build.
+  fn update(&self, sqlite: &sqlite::SQLite, since: &String) {
+    let file_regex = Regex::new(r"^(\d+)\.geojson$").unwrap();
+    let mut documents: BTreeSet<(i64, PathBuf)> = BTreeSet::new();
+    for directory in &self.directories {
+      let directory = Path::new(directory)
+        .canonicalize()
+        .expect_exit(&format!("Can't find directory `{}`", directory));
+      let git = crate::git::Git::open(&directory);
+      for path in git.get_changes_from_range(since, &"HEAD".to_string()) {
+        if !path.starts_with(&directory) {
+          continue;
+        }
+        let id = path
+          .file_name()
+          .and_then(|name| name.to_str())
+          .and_then(|name| file_regex.captures(name))
+          .and_then(|captures| captures[1].parse::<i64>().ok());
+        if let (Some(id), Some(folder)) = (id, path.parent()) {
+          documents.insert((id, folder.to_path_buf()));
+        }
+      }
+    }
+    info!("{} documents changed since {}", documents.len(), since);
+    sqlite.begin().expect_exit("Can't start a transaction");
+    let (mut updated, mut deleted) = (0, 0);
+    for (id, folder) in documents {
+      // Nothing is committed on failure, the database is left as it was before the update.
+      sqlite
+        .delete(id)
+        .expect_exit(&format!("Can't delete {}", id));
+      let path = folder.join(format!("{}.geojson", id));
+      if path.exists() {
+        updated += 1;
+        sqlite
+          .add_file(&path)
+          .expect_exit(&format!("Can't add {}", path.display()));
+      } else {
+        deleted += 1;
+      }
+    }
+    sqlite.commit().expect_exit("Can't commit a transaction");
+    info!(
+      "{} documents updated, {} documents deleted.",
+      updated, deleted
+    );
+  }
+}
+
+/// An update fills the optional tables the database already has, whatever the flags, so no table
+/// keeps the old rows of a changed document.
+fn existing_tables(path: &Path, opts: sqlite::SQLiteOpts) -> Result<sqlite::SQLiteOpts, String> {
+  let mut database = sqlite::SQLite::new(path, opts.clone())?;
+  database.use_existing_tables()?;
+  let existing = database.opts();
+  for (table, enabled, exists) in [
+    ("search", opts.search, existing.search),
+    ("rtree", opts.rtree, existing.rtree),
+    ("properties", opts.properties, existing.properties),
+    ("geometries", opts.geometries, existing.geometries),
+  ] {
+    if enabled && !exists {
+      warn!(
+        "`--{}` is ignored, the database has no {} table",
+        table, table
+      );
+    } else if !enabled && exists {
+      warn!(
+        "The {} table of the database is updated without `--{}`",
+        table, table
+      );
+    }
+  }
+  Ok(existing.clone())
+}
+
+/// The database is built next to the final one, so it can be renamed over it. The name does not
+/// change between builds, the file left by an interrupted build is removed by the next one.
+fn temporary_path(out_path: &Path) -> PathBuf {
+  let file_name = out_path
+    .file_name()
+    .map(|name| name.to_string_lossy().to_string())
+    .unwrap_or_default();
+  out_path.with_file_name(format!(".{}.tmp", file_name))
+}
+
+/// Rename the new database over the old one, readers never see a partial database. The WAL
+/// journal left by a safe mode update of the old database is emptied first, so it is not
+/// applied to the new one. The file and the rename are synced to disk, a crash can't leave a
+/// truncated database behind the final name.
+fn replace_database(temporary_path: &Path, out_path: &Path) -> Result<(), String> {
+  let mut wal_path = out_path.as_os_str().to_os_string();
+  wal_path.push("-wal");
+  if out_path.exists() && Path::new(&wal_path).exists() {
+    sqlite::SQLite::new(out_path, sqlite::SQLiteOpts::default())?.checkpoint()?;
+  }
+  std::fs::File::open(temporary_path)
+    .and_then(|file| file.sync_all())
+    .stringify_err("Can't sync the temporary database")?;
+  std::fs::rename(temporary_path, out_path).stringify_err("Can't rename the temporary database")?;
+  if let Some(parent) = out_path.parent() {
+    let parent = if parent.as_os_str().is_empty() {
+      Path::new(".")
+    } else {
+      parent
+    };
+    std::fs::File::open(parent)
+      .and_then(|directory| directory.sync_all())
+      .stringify_err("Can't sync the database directory")?;
+  }
+  Ok(())
+}
+
+impl Destination for sqlite::SQLite {
+  fn add(&mut self, document: WOFGeoJSON) -> Result<(), String> {
+    sqlite::SQLite::add(self, document)
+  }
+
+  fn begin(&mut self) -> Result<(), String> {
+    sqlite::SQLite::begin(self)
+  }
+
+  fn commit(&mut self) -> Result<(), String> {
+    sqlite::SQLite::commit(self)
+  }
+}
+
+#[cfg(test)]
+mod test_sqlite {
+  use super::*;
+  use json::{object, JsonValue};
+  use rusqlite::types::Value;
+  use rusqlite::Connection;
+
+  fn document(id: i32, name: &str, alt: bool) -> JsonValue {
+    let mut json = object! {
+      "type" => "Feature",
+      "properties" => object!{
+        "wof:id" => id,
+        "wof:name" => name,
+        "wof:placetype" => "locality",
+        "name:fra_x_preferred" => vec![name],
+        "wof:hierarchy" => vec![object!{ "country_id" => 85633147 }],
+        "mz:is_current" => 1,
+      },
+      "geometry" => object!{
+        "coordinates" => vec![vec![
+          vec![8.5, 41.8], vec![8.8, 41.8], vec![8.8, 41.9], vec![8.5, 41.8]
+        ]],
+        "type" => "Polygon"
+      },
+      "bbox" => vec![8.5, 41.8, 8.8, 41.9],
+      "id" => id,
+    };
+    if alt {
+      json["properties"]["src:alt_label"] = "quattroshapes".into();
+      json["properties"]["src:geom"] = "quattroshapes".into();
+    }
+    json
+  }
+
+  fn commit(repository: &git2::Repository, message: &str) {
+    let mut index = repository.index().unwrap();
+    index
+      .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+      .unwrap();
+    index.update_all(["*"], None).unwrap();
+    index.write().unwrap();
+    let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = git2::Signature::now("wof", "wof@example.com").unwrap();
+    let parents: Vec<git2::Commit> = repository
+      .head()
+      .ok()
+      .and_then(|head| head.peel_to_commit().ok())
+      .into_iter()
+      .collect();
+    let parents: Vec<&git2::Commit> = parents.iter().collect();
+    repository
+      .commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+      )
+      .unwrap();
+  }
+
+  fn open(path: &Path) -> sqlite::SQLite {
     let sqlite = sqlite::SQLite::new(
-      out_path,
+      path,
       sqlite::SQLiteOpts {
-        pretty: !self.no_pretty,
-        deprecated: !self.no_deprecated,
-        names: !pelias_preset,
-        ancestors: !pelias_preset,
-        concordances: !pelias_preset,
+        search: true,
+        rtree: true,
+        properties: true,
+        geometries: true,
         ..Default::default()
       },
     )
-    .expect_exit("Can't open the database");
+    .unwrap();
+    sqlite.create_tables().unwrap();
+    sqlite
+  }
 
-    info!("Creating tables and indexes.");
-    sqlite.create_tables().expect_exit("Can't create tables");
+  /// Same files as `build_database`.
+  fn build(directory: &Path, path: &Path) {
+    let sqlite = open(path);
+    for entry in crate::repo::Walk::new(directory, false, true) {
+      sqlite.add_file(entry.unwrap().path()).unwrap();
+    }
+  }
 
-    crate::commands::build::build_database(&self.directories, self.timings, &mut |buffer, file| {
-      if let Some(buffer) = buffer {
-        sqlite.add_string(buffer)
-      } else if let Some(file) = file {
-        sqlite.add_file(file)
-      } else {
-        Ok(())
+  fn rows(path: &Path) -> Vec<String> {
+    let connection = Connection::open(path).unwrap();
+    let mut rows = vec![];
+    for table in [
+      "geojson",
+      "spr",
+      "names",
+      "ancestors",
+      "concordances",
+      "search",
+      "rtree",
+      "properties",
+      "geometries",
+    ] {
+      let mut statement = connection
+        .prepare(&format!("SELECT * FROM {}", table))
+        .unwrap();
+      let columns = statement.column_count();
+      let mut query = statement.query([]).unwrap();
+      while let Some(row) = query.next().unwrap() {
+        // The rowid of the rtree depends on the order of the inserts.
+        let first = if table == "rtree" { 1 } else { 0 };
+        let values: Vec<Value> = (first..columns)
+          .map(|i| match row.get(i).unwrap() {
+            Value::Blob(blob) => Value::Text(String::from_utf8(blob).unwrap()),
+            value => value,
+          })
+          .collect();
+        rows.push(format!("{} {:?}", table, values));
       }
-    });
+    }
+    rows.sort();
+    rows
+  }
+
+  #[test]
+  fn update_like_a_full_build() {
+    let directory = std::env::temp_dir().join(format!("wof-update-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let repository = git2::Repository::init(&directory).unwrap();
+    let write = |file: &str, json: JsonValue| {
+      std::fs::write(directory.join(file), json.dump()).unwrap();
+    };
+    write("1.geojson", document(1, "Ajaccio", false));
+    write("1-alt-quattroshapes.geojson", document(1, "Ajaccio", true));
+    write("2.geojson", document(2, "Bastia", false));
+    commit(&repository, "init");
+    let updated = directory.join("updated.db");
+    build(&directory, &updated);
+
+    write("1.geojson", document(1, "Aiacciu", false));
+    write("1-alt-quattroshapes.geojson", document(1, "Aiacciu", true));
+    write("3.geojson", document(3, "Corte", false));
+    write("3-alt-quattroshapes.geojson", document(3, "Corte", true));
+    write("4.geojson", document(4, "Calvi", false));
+    std::fs::remove_file(directory.join("2.geojson")).unwrap();
+    commit(&repository, "change");
+
+    let command = SQLite::parse_from([
+      "sqlite",
+      directory.to_str().unwrap(),
+      "--update",
+      "--since",
+      "HEAD~1",
+    ]);
+    command.update(&open(&updated), &"HEAD~1".to_string());
+    let rebuilt = directory.join("rebuilt.db");
+    build(&directory, &rebuilt);
+    let (updated, rebuilt) = (rows(&updated), rows(&rebuilt));
+    std::fs::remove_dir_all(&directory).unwrap();
+    assert_eq!(updated, rebuilt);
+    assert!(updated.iter().any(|row| row.contains("Aiacciu")));
+    assert!(!updated.iter().any(|row| row.contains("Bastia")));
   }
 }
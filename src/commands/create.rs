@@ -0,0 +1,129 @@
+use crate::create::{prepare_document, set_document_id, FileIdProvider, IdProvider};
+use crate::pip::parse_coordinate;
+use crate::std::StringifyError;
+use crate::utils::ResultExit;
+use crate::wof::WOFGeoJSON;
+use crate::JsonValue;
+use clap::Parser;
+use log::{error, info};
+use std::fs::File;
+use std::io::{stdin, stdout, Read, Write};
+use std::path::Path;
+
+#[derive(Debug, Parser)]
+pub struct Create {
+  /// Minimal GeoJSON Feature of the new document, read from stdin when omitted.
+  pub input: Option<String>,
+  /// Name of the new document, sets `wof:name`.
+  #[arg(long = "name")]
+  pub name: Option<String>,
+  /// Placetype of the new document, sets `wof:placetype`.
+  #[arg(long = "placetype")]
+  pub placetype: Option<String>,
+  /// Point geometry of the new document, as `lon,lat`.
+  #[arg(long = "point", allow_hyphen_values = true)]
+  pub point: Option<String>,
+  /// Id of the new document instead of an allocated one.
+  #[arg(long = "id")]
+  pub id: Option<i64>,
+  /// File keeping the last allocated id.
+  #[arg(long = "id-file", default_value = ".wof-id")]
+  pub id_file: String,
+  /// Range of ids to allocate, as `start-end`.
+  #[arg(long = "id-range")]
+  pub id_range: Option<String>,
+  /// Repository where the document is written.
+  #[arg(short = 'o', long = "out", default_value = ".")]
+  pub out: String,
+  /// Activate verbose mode.
+  #[arg(short = 'v', long = "verbose")]
+  pub verbose: bool,
+}
+
+impl Create {
+  pub fn exec(&self) {
+    crate::utils::logger::set_verbose(self.verbose, "wof::create")
+      .expect_exit("Can't init logger.");
+    let json = self.read_input().expect_exit("Can't read the new document");
+    let mut document = prepare_document(&json).expect_exit("Can't create the document");
+    let mut provider = self.id_provider().expect_exit("Can't allocate ids");
+    let (id, path) = loop {
+      let id = match self.id {
+        Some(id) => id,
+        None => provider.next_id().expect_exit("Can't allocate an id"),
+      };
+      let path = Path::new(&self.out).join(crate::utils::id_to_data_path_geojson(id));
+      if !path.exists() {
+        break (id, path);
+      }
+      if self.id.is_some() {
+        error!("{} already exists", path.display());
+        std::process::exit(1);
+      }
+      info!("{} already exists, allocating another id", path.display());
+    };
+    set_document_id(&mut document, id);
+    self
+      .write(&document, &path)
+      .expect_exit(&format!("Can't write {}", path.display()));
+    writeln!(stdout(), "{}", path.display()).exit_silently();
+  }
+
+  fn read_input(&self) -> Result<JsonValue, String> {
+    let mut json = match &self.input {
+      Some(path) => crate::parse_file_to_json(path)?,
+      None => {
+        let mut buffer = String::new();
+        if crate::commands::input_pipe() {
+          stdin()
+            .read_to_string(&mut buffer)
+            .stringify_err("Can't read stdin")?;
+        }
+        if buffer.trim().is_empty() {
+          json::object! { "type" => "Feature", "properties" => JsonValue::new_object() }
+        } else {
+          crate::parse_string_to_json(&buffer)?
+        }
+      }
+    };
+    if json["properties"].is_null() {
+      json["properties"] = JsonValue::new_object();
+    }
+    if let Some(name) = &self.name {
+      json["properties"]["wof:name"] = name.as_str().into();
+    }
+    if let Some(placetype) = &self.placetype {
+      json["properties"]["wof:placetype"] = placetype.as_str().into();
+    }
+    if let Some(point) = &self.point {
+      let (lon, lat) = parse_coordinate(point)?;
+      json["geometry"] = json::object! { "type" => "Point", "coordinates" => vec![lon, lat] };
+    }
+    Ok(json)
+  }
+
+  fn id_provider(&self) -> Result<Box<dyn IdProvider>, String> {
+    let provider = match &self.id_range {
+      Some(range) => {
+        let (start, end) = range
+          .split_once('-')
+          .and_then(|(start, end)| Some((start.trim().parse().ok()?, end.trim().parse().ok()?)))
+          .ok_or(format!("`{}` is not a `start-end` range", range))?;
+        FileIdProvider::with_range(&self.id_file, start, end)?
+      }
+      None => FileIdProvider::new(&self.id_file),
+    };
+    Ok(Box::new(provider))
+  }
+
+  fn write(&self, document: &JsonValue, path: &Path) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent).stringify_err("Can't create the directory")?;
+    }
+    let wof_obj = WOFGeoJSON::as_valid_wof_geojson(document)?;
+    let mut file = File::create(path).stringify_err("Can't create the file")?;
+    crate::ser::wof_to_writer_pretty(&wof_obj, &mut file).stringify_err("Can't write the file")?;
+    info!("{} created", path.display());
+    Ok(())
+  }
+}
@@ -0,0 +1,169 @@
+use crate::diff::{diff_documents, DocumentDiff};
+use crate::repo::Walk;
+use crate::sqlite::{SQLite, SQLiteOpts};
+use crate::utils::ResultExit;
+use crate::JsonValue;
+use clap::builder::PossibleValuesParser;
+use clap::Parser;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{stdout, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Parser)]
+pub struct Diff {
+  /// The old version: a WOF document, a repository or a SQLite database.
+  pub before: String,
+  /// The new version: a WOF document, a repository or a SQLite database.
+  pub after: String,
+  /// Output format, `patch` prints lines that `wof patch` can apply on the old version.
+  #[arg(
+    long = "format",
+    default_value = "text",
+    value_parser = PossibleValuesParser::new(&["text", "json", "patch"]),
+  )]
+  pub format: String,
+}
+
+/// Where the documents are read from, repositories and databases are read by id.
+enum Source {
+  File(PathBuf),
+  Directory(BTreeMap<i64, PathBuf>),
+  SQLite(SQLite),
+}
+
+impl Source {
+  fn open(path: &str) -> Result<Self, String> {
+    let path = Path::new(path);
+    if path.is_dir() {
+      let mut paths = BTreeMap::new();
+      for entry in Walk::new(path, false, true) {
+        let entry = entry.map_err(|e| format!("Can't walk through {}: {}", path.display(), e))?;
+        let id = entry
+          .path()
+          .file_stem()
+          .and_then(|stem| stem.to_str())
+          .and_then(|stem| stem.parse::<i64>().ok());
+        if let Some(id) = id {
+          paths.insert(id, entry.path().to_path_buf());
+        }
+      }
+      Ok(Source::Directory(paths))
+    } else if path.extension().is_some_and(|ext| ext == "geojson") {
+      Ok(Source::File(path.to_path_buf()))
+    } else {
+      let opts = SQLiteOpts {
+        alt: false,
+        ..Default::default()
+      };
+      Ok(Source::SQLite(SQLite::new(path, opts)?))
+    }
+  }
+
+  fn ids(&self) -> Result<BTreeSet<i64>, String> {
+    match self {
+      Source::File(path) => Ok(
+        crate::parse_file_to_json(path)?["id"]
+          .as_i64()
+          .into_iter()
+          .collect(),
+      ),
+      Source::Directory(paths) => Ok(paths.keys().copied().collect()),
+      Source::SQLite(sqlite) => {
+        let mut ids = BTreeSet::new();
+        sqlite.for_each_id(|id| {
+          ids.insert(id);
+          Ok(())
+        })?;
+        Ok(ids)
+      }
+    }
+  }
+
+  fn get(&self, id: i64) -> Result<Option<JsonValue>, String> {
+    match self {
+      Source::File(path) => {
+        let json = crate::parse_file_to_json(path)?;
+        Ok(Some(json).filter(|json| json["id"].as_i64() == Some(id)))
+      }
+      Source::Directory(paths) => paths.get(&id).map(crate::parse_file_to_json).transpose(),
+      Source::SQLite(sqlite) => sqlite.get_geojson_by_id(id),
+    }
+  }
+}
+
+impl Diff {
+  pub fn exec(&self) {
+    crate::utils::logger::set_verbose(false, "wof::diff").expect_exit("Can't init logger.");
+    let before = Source::open(&self.before).expect_exit(&format!("Can't open {}", self.before));
+    let after = Source::open(&self.after).expect_exit(&format!("Can't open {}", self.after));
+    if let (Source::File(before), Source::File(after)) = (&before, &after) {
+      let before = crate::parse_file_to_json(before).expect_exit("Can't read the old document");
+      let after = crate::parse_file_to_json(after).expect_exit("Can't read the new document");
+      let diff = diff_documents(&before, &after).expect_exit("Can't compare the documents");
+      return self.print_diff(&diff);
+    }
+    let mut ids = before.ids().expect_exit("Can't read the old ids");
+    ids.extend(after.ids().expect_exit("Can't read the new ids"));
+    for id in ids {
+      let message = format!("Can't compare {}", id);
+      match (
+        before.get(id).expect_exit(&message),
+        after.get(id).expect_exit(&message),
+      ) {
+        (Some(before), Some(after)) => {
+          self.print_diff(&diff_documents(&before, &after).expect_exit(&message))
+        }
+        (None, Some(_)) => self.print_document_status(id, "added"),
+        (Some(_), None) => self.print_document_status(id, "removed"),
+        (None, None) => (),
+      }
+    }
+  }
+
+  fn print_diff(&self, diff: &DocumentDiff) {
+    if diff.is_empty() {
+      return;
+    }
+    let mut stdout = stdout();
+    match self.format.as_str() {
+      "json" => writeln!(stdout, "{}", diff.to_json().dump()).exit_silently(),
+      "patch" => writeln!(stdout, "{}", diff.to_patch().dump()).exit_silently(),
+      _ => {
+        writeln!(stdout, "{}:", diff.id).exit_silently();
+        for (key, value) in &diff.added {
+          writeln!(stdout, "  + {}: {}", key, value.dump()).exit_silently();
+        }
+        for key in &diff.removed {
+          writeln!(stdout, "  - {}", key).exit_silently();
+        }
+        for (key, before, after) in &diff.changed {
+          writeln!(stdout, "  ~ {}: {} -> {}", key, before.dump(), after.dump()).exit_silently();
+        }
+        if let Some((before, after)) = &diff.bbox {
+          writeln!(stdout, "  ~ bbox: {} -> {}", before.dump(), after.dump()).exit_silently();
+        }
+        if let Some(geometry) = &diff.geometry {
+          writeln!(
+            stdout,
+            "  ~ geometry: {} -> {} vertices, area {:+.2} m², centroid moved {:.3} km",
+            geometry.vertices.0,
+            geometry.vertices.1,
+            geometry.area_delta_m,
+            geometry.centroid_shift_km
+          )
+          .exit_silently();
+        }
+      }
+    }
+  }
+
+  /// Documents only in one version can't be patched, they are comments in the patch format.
+  fn print_document_status(&self, id: i64, status: &str) {
+    let line = match self.format.as_str() {
+      "json" => json::object! { "id" => id, "status" => status }.dump(),
+      "patch" => format!("# {} {}", id, status),
+      _ => format!("{}: {}", id, status),
+    };
+    writeln!(stdout(), "{}", line).exit_silently();
+  }
+}
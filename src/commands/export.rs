@@ -0,0 +1,154 @@
+use crate::export::{export_changes, export_json_value_with_options, ExportOptions};
+use crate::repo::Walk;
+use crate::utils::ResultExit;
+use crate::wof::WOFGeoJSON;
+use crate::JsonValue;
+use clap::Parser;
+use log::{error, info};
+use std::io::{stdin, stdout, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Parser)]
+pub struct Export {
+  /// Paths to WOF documents or directories to export in place. Documents can also be sent via stdin.
+  pub paths: Vec<String>,
+  /// Run export on all staged files (needs git repository).
+  #[arg(long = "stagged", alias = "staged")]
+  pub stagged: bool,
+  /// Don't write anything, only show which properties would change.
+  #[arg(long = "dry-run")]
+  pub dry_run: bool,
+  /// Don't recompute geometry properties when the geometry changed.
+  #[arg(long = "no-geometry")]
+  pub no_geometry: bool,
+  /// Don't derive `wof:belongsto` from `wof:hierarchy`.
+  #[arg(long = "no-belongsto")]
+  pub no_belongsto: bool,
+  /// Don't set `mz:is_current` from the EDTF dates.
+  #[arg(long = "no-is-current")]
+  pub no_is_current: bool,
+  /// Keep the `wof:lastmodified` of the documents.
+  #[arg(long = "no-lastmodified")]
+  pub no_lastmodified: bool,
+  /// Activate verbose mode.
+  #[arg(short = 'v', long = "verbose")]
+  pub verbose: bool,
+}
+
+impl Export {
+  pub fn exec(&self) {
+    crate::utils::logger::set_verbose(self.verbose, "wof::export")
+      .expect_exit("Can't init logger.");
+    if self.paths.is_empty() && !self.stagged && crate::commands::input_pipe() {
+      return self.export_stdin();
+    }
+    let mut failures = 0;
+    for path in &self.paths {
+      if Path::new(path).is_dir() {
+        for entry in Walk::new(path, false, true) {
+          match entry {
+            Ok(entry) => failures += self.export_file(entry.path()),
+            Err(e) => {
+              error!("Can't walk through {}: {}", path, e);
+              failures += 1;
+            }
+          }
+        }
+      } else {
+        failures += self.export_file(Path::new(path));
+      }
+    }
+    if self.stagged {
+      let paths: Vec<PathBuf> = crate::git::Git::new()
+        .get_changes_from_stagged()
+        .into_iter()
+        .filter(|path| path.exists() && path.extension() == Some(std::ffi::OsStr::new("geojson")))
+        .collect();
+      for path in paths {
+        failures += self.export_file(&path);
+      }
+    }
+    if failures > 0 {
+      std::process::exit(1);
+    }
+  }
+
+  /// Export each document of stdin and print it minified on stdout.
+  fn export_stdin(&self) {
+    loop {
+      let mut input = String::new();
+      match stdin().read_line(&mut input) {
+        Ok(0) => break,
+        Ok(_) => {
+          if input.trim().is_empty() {
+            continue;
+          }
+          let json = crate::parse_string_to_json(&input).expect_exit("Malformed json object");
+          let exported = self.export(&json).expect_exit("Can't export the document");
+          if self.dry_run {
+            self.print_changes("stdin", &json, &exported);
+          } else {
+            crate::ser::json_to_writer(&exported, &mut stdout()).exit_silently();
+            writeln!(stdout()).exit_silently();
+          }
+        }
+        Err(_) => break,
+      }
+    }
+  }
+
+  /// Export a document in place, returns 1 when it failed.
+  fn export_file(&self, path: &Path) -> usize {
+    match self.try_export_file(path) {
+      Ok(()) => 0,
+      Err(e) => {
+        error!("Can't export {}: {}", path.display(), e);
+        1
+      }
+    }
+  }
+
+  fn try_export_file(&self, path: &Path) -> Result<(), String> {
+    let json = crate::parse_file_to_json(path)?;
+    let exported = self.export(&json)?;
+    if self.dry_run {
+      self.print_changes(&path.display().to_string(), &json, &exported);
+      return Ok(());
+    }
+    if export_changes(&json, &exported).is_empty() {
+      info!("{} is up to date", path.display());
+      return Ok(());
+    }
+    let wof_obj = WOFGeoJSON::as_valid_wof_geojson(&exported)?;
+    crate::commands::write_wof_file(path, &wof_obj)?;
+    info!("{} exported", path.display());
+    Ok(())
+  }
+
+  fn print_changes(&self, name: &str, json: &JsonValue, exported: &JsonValue) {
+    let changes = export_changes(json, exported);
+    if changes.is_empty() {
+      info!("{} is up to date", name);
+      return;
+    }
+    writeln!(stdout(), "{}:", name).exit_silently();
+    for change in changes {
+      writeln!(stdout(), "  {}", change).exit_silently();
+    }
+  }
+
+  /// Alternate geometries don't get the defaults and `wof:belongsto` of main documents.
+  fn export(&self, json: &JsonValue) -> Result<JsonValue, String> {
+    let is_alt = WOFGeoJSON::as_valid_wof_geojson(json).is_ok_and(|wof| wof.is_alt_geom());
+    export_json_value_with_options(
+      json,
+      &ExportOptions {
+        defaults: !is_alt,
+        geometry: !self.no_geometry,
+        belongsto: !self.no_belongsto && !is_alt,
+        is_current: !self.no_is_current,
+        lastmodified: !self.no_lastmodified,
+      },
+    )
+  }
+}
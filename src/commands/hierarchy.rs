@@ -0,0 +1,115 @@
+use crate::diff::diff_documents;
+use crate::export::{export_json_value_with_options, ExportOptions};
+use crate::hierarchy::HierarchyResolver;
+use crate::placetypes::get_rank;
+use crate::repo::Walk;
+use crate::utils::ResultExit;
+use crate::wof::WOFGeoJSON;
+use clap::Parser;
+use log::{error, info, warn};
+use std::io::{stdout, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Parser)]
+pub struct Hierarchy {
+  /// Paths to WOF repositories, their polygons are the candidate parents of all documents.
+  #[arg(default_value = ".")]
+  pub paths: Vec<String>,
+  /// Don't write anything, only show the new parents and hierarchies.
+  #[arg(long = "dry-run")]
+  pub dry_run: bool,
+  /// Activate verbose mode.
+  #[arg(short = 'v', long = "verbose")]
+  pub verbose: bool,
+}
+
+impl Hierarchy {
+  pub fn exec(&self) {
+    crate::utils::logger::set_verbose(self.verbose, "wof::hierarchy")
+      .expect_exit("Can't init logger.");
+    let mut resolver = HierarchyResolver::new();
+    let mut documents: Vec<(u8, i64, PathBuf)> = vec![];
+    for path in &self.paths {
+      for entry in Walk::new(path, false, false) {
+        let entry = entry.expect_exit(&format!("Can't walk through {}", path));
+        let json = crate::parse_file_to_json(entry.path())
+          .expect_exit(&format!("Can't read {}", entry.path().display()));
+        resolver
+          .add(&json)
+          .expect_exit(&format!("Can't index {}", entry.path().display()));
+        let wof_obj = WOFGeoJSON::as_valid_wof_geojson(&json)
+          .expect_exit(&format!("Can't read {}", entry.path().display()));
+        if let (Some(rank), Some(id)) = (get_rank(&wof_obj.get_placetype()), json["id"].as_i64()) {
+          documents.push((rank, id, entry.path().to_path_buf()));
+        }
+      }
+    }
+    if resolver.is_empty() {
+      warn!("No polygons found, no parent can be resolved");
+    }
+    info!("{} polygons indexed", resolver.len());
+    // Parents are resolved before their children to use their new hierarchies.
+    documents.sort();
+    let mut failures = 0;
+    for (_, id, path) in documents {
+      if let Err(e) = self.rebuild_file(&mut resolver, id, &path) {
+        error!("Can't rebuild the hierarchy of {}: {}", path.display(), e);
+        failures += 1;
+      }
+    }
+    if failures > 0 {
+      std::process::exit(1);
+    }
+  }
+
+  fn rebuild_file(
+    &self,
+    resolver: &mut HierarchyResolver,
+    id: i64,
+    path: &Path,
+  ) -> Result<(), String> {
+    let json = crate::parse_file_to_json(path)?;
+    let resolution = match resolver.resolve(&json)? {
+      Some(resolution) => resolution,
+      None => {
+        info!("No parent found for {}", path.display());
+        return Ok(());
+      }
+    };
+    resolver.update(id, &resolution);
+    let mut resolved = json.clone();
+    resolution.apply(&mut resolved)?;
+    if diff_documents(&json, &resolved)?.is_empty() {
+      info!("{} is up to date", path.display());
+      return Ok(());
+    }
+    // Only the properties derived from the hierarchy are recomputed.
+    let options = ExportOptions {
+      defaults: false,
+      geometry: false,
+      belongsto: true,
+      is_current: false,
+      lastmodified: true,
+    };
+    let exported = export_json_value_with_options(&resolved, &options)?;
+    if self.dry_run {
+      let diff = diff_documents(&json, &exported)?;
+      let mut stdout = stdout();
+      writeln!(stdout, "{}:", path.display()).exit_silently();
+      for (key, value) in &diff.added {
+        writeln!(stdout, "  + {}: {}", key, value.dump()).exit_silently();
+      }
+      for key in &diff.removed {
+        writeln!(stdout, "  - {}", key).exit_silently();
+      }
+      for (key, before, after) in &diff.changed {
+        writeln!(stdout, "  ~ {}: {} -> {}", key, before.dump(), after.dump()).exit_silently();
+      }
+      return Ok(());
+    }
+    let wof_obj = WOFGeoJSON::as_valid_wof_geojson(&exported)?;
+    crate::commands::write_wof_file(path, &wof_obj)?;
+    info!("{} hierarchy rebuilt", path.display());
+    Ok(())
+  }
+}
@@ -0,0 +1,57 @@
+use crate::integrity::IdIndex;
+use crate::utils::ResultExit;
+use clap::builder::PossibleValuesParser;
+use clap::Parser;
+use log::{info, warn};
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Debug, Parser)]
+pub struct Integrity {
+  /// Paths to WOF repositories or SQLite databases, all documents are checked together.
+  #[arg(default_value = ".")]
+  pub paths: Vec<String>,
+  /// Output format of the report.
+  #[arg(
+    long = "format",
+    default_value = "text",
+    value_parser = PossibleValuesParser::new(&["text", "json"]),
+  )]
+  pub format: String,
+  /// Activate verbose mode.
+  #[arg(short = 'v', long = "verbose")]
+  pub verbose: bool,
+}
+
+impl Integrity {
+  pub fn exec(&self) {
+    crate::utils::logger::set_verbose(self.verbose, "wof::integrity")
+      .expect_exit("Can't init logger.");
+    let mut index = IdIndex::new();
+    for path in &self.paths {
+      if Path::new(path).is_dir() {
+        index.add_directory(path)
+      } else {
+        index.add_sqlite(path)
+      }
+      .expect_exit(&format!("Can't index `{}`", path));
+    }
+    if index.is_empty() {
+      warn!("No WOF documents found");
+    }
+    info!("{} documents indexed", index.len());
+    let errors = index.check();
+    let mut stdout = std::io::stdout();
+    for error in &errors {
+      if self.format == "json" {
+        writeln!(stdout, "{}", error.to_json().dump()).exit_silently();
+      } else {
+        writeln!(stdout, "{}: [{}] {}", error.id, error.rule, error.message).exit_silently();
+      }
+    }
+    info!("{} problems found", errors.len());
+    if !errors.is_empty() {
+      std::process::exit(1);
+    }
+  }
+}
@@ -0,0 +1,203 @@
+use crate::create::{FileIdProvider, IdProvider};
+use crate::export::{export_json_value_with_options, ExportOptions};
+use crate::lifecycle::{cessate, clone_successor, deprecate, supersede};
+use crate::sqlite::{SQLite, SQLiteOpts};
+use crate::std::StringifyError;
+use crate::utils::{self, ResultExit};
+use crate::wof::WOFGeoJSON;
+use crate::JsonValue;
+use clap::Parser;
+use log::info;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Parser)]
+pub struct Deprecate {
+  /// Ids of the documents to deprecate.
+  #[arg(required = true)]
+  pub ids: Vec<i64>,
+  /// WOF repository or SQLite database with the documents.
+  #[arg(short = 's', long = "source", default_value = ".")]
+  pub source: String,
+  /// EDTF date of the deprecation, today by default.
+  #[arg(long = "date")]
+  pub date: Option<String>,
+  /// Set `edtf:cessation` instead of `edtf:deprecated`, for places that existed and no longer do.
+  #[arg(long = "cessate")]
+  pub cessate: bool,
+  /// Activate verbose mode.
+  #[arg(short = 'v', long = "verbose")]
+  pub verbose: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct Supersede {
+  /// Id of the superseded document.
+  pub old: i64,
+  /// Id of the existing successor.
+  #[arg(
+    long = "by",
+    conflicts_with = "clone",
+    required_unless_present = "clone"
+  )]
+  pub by: Option<i64>,
+  /// Create the successor as a copy of the superseded document.
+  #[arg(long = "clone")]
+  pub clone: bool,
+  /// Id of the cloned successor instead of an allocated one.
+  #[arg(long = "id", requires = "clone")]
+  pub id: Option<i64>,
+  /// File keeping the last allocated id.
+  #[arg(long = "id-file", default_value = ".wof-id")]
+  pub id_file: String,
+  /// WOF repository or SQLite database with the documents.
+  #[arg(short = 's', long = "source", default_value = ".")]
+  pub source: String,
+  /// Activate verbose mode.
+  #[arg(short = 'v', long = "verbose")]
+  pub verbose: bool,
+}
+
+/// Documents are read by id and written back exported, with a new `wof:lastmodified`.
+enum Records {
+  Directory(PathBuf),
+  SQLite(SQLite),
+}
+
+impl Records {
+  fn open(path: &str) -> Result<Self, String> {
+    if Path::new(path).is_dir() {
+      Ok(Records::Directory(PathBuf::from(path)))
+    } else {
+      let mut sqlite = SQLite::new(path, SQLiteOpts::default())?;
+      sqlite.use_existing_tables()?;
+      Ok(Records::SQLite(sqlite))
+    }
+  }
+
+  /// Writes until `commit` are applied together to a database, files are written one by one.
+  fn begin(&self) -> Result<(), String> {
+    match self {
+      Records::Directory(_) => Ok(()),
+      Records::SQLite(sqlite) => sqlite.begin(),
+    }
+  }
+
+  fn commit(&self) -> Result<(), String> {
+    match self {
+      Records::Directory(_) => Ok(()),
+      Records::SQLite(sqlite) => sqlite.commit(),
+    }
+  }
+
+  fn get(&self, id: i64) -> Result<JsonValue, String> {
+    match self {
+      Records::Directory(directory) => {
+        let path = utils::get_geojson_path_from_id(directory, id)
+          .ok_or(format!("GeoJSON {} not found", id))?;
+        crate::parse_file_to_json(path)
+      }
+      Records::SQLite(sqlite) => sqlite
+        .get_geojson_by_id(id)?
+        .ok_or(format!("GeoJSON {} not found", id)),
+    }
+  }
+
+  fn contains(&self, id: i64) -> Result<bool, String> {
+    match self {
+      Records::Directory(directory) => Ok(utils::get_geojson_path_from_id(directory, id).is_some()),
+      Records::SQLite(sqlite) => Ok(sqlite.get_geojson_by_id(id)?.is_some()),
+    }
+  }
+
+  fn put(&self, json: &JsonValue) -> Result<(), String> {
+    let exported = export_json_value_with_options(json, &ExportOptions::default())?;
+    let wof = WOFGeoJSON::as_valid_wof_geojson(&exported)?;
+    match self {
+      Records::Directory(directory) => {
+        let path = match utils::get_geojson_path_from_id(directory, wof.id) {
+          Some(path) => path,
+          None if directory.join("data").is_dir() => {
+            directory.join(utils::id_to_data_path_geojson(wof.id))
+          }
+          None => directory.join(utils::id_to_path_geojson(wof.id)),
+        };
+        if let Some(parent) = path.parent() {
+          std::fs::create_dir_all(parent).stringify_err("Can't create the directory")?;
+        }
+        crate::commands::write_wof_file(&path, &wof)?;
+        info!("{} written", path.display());
+      }
+      Records::SQLite(sqlite) => {
+        sqlite.set_geojson_alt(wof.id, &wof.get_source(), 1)?;
+        sqlite.add(wof)?;
+      }
+    }
+    Ok(())
+  }
+}
+
+impl Deprecate {
+  pub fn exec(&self) {
+    crate::utils::logger::set_verbose(self.verbose, "wof::deprecate")
+      .expect_exit("Can't init logger.");
+    let records = Records::open(&self.source).expect_exit(&format!("Can't open {}", self.source));
+    let date = self.date.clone().unwrap_or_else(today);
+    for id in &self.ids {
+      let message = format!("Can't deprecate {}", id);
+      let mut json = records.get(*id).expect_exit(&message);
+      if self.cessate {
+        cessate(&mut json, &date)
+      } else {
+        deprecate(&mut json, &date)
+      }
+      .expect_exit(&message);
+      records.put(&json).expect_exit(&message);
+    }
+  }
+}
+
+impl Supersede {
+  pub fn exec(&self) {
+    crate::utils::logger::set_verbose(self.verbose, "wof::supersede")
+      .expect_exit("Can't init logger.");
+    let records = Records::open(&self.source).expect_exit(&format!("Can't open {}", self.source));
+    let message = format!("Can't supersede {}", self.old);
+    let mut old = records.get(self.old).expect_exit(&message);
+    let mut new = match self.by {
+      Some(by) => records.get(by).expect_exit(&message),
+      None => {
+        let id = self
+          .successor_id(&records)
+          .expect_exit("Can't allocate an id");
+        clone_successor(&old, id).expect_exit(&message)
+      }
+    };
+    supersede(&mut old, &mut new).expect_exit(&message);
+    records.begin().expect_exit(&message);
+    records.put(&new).expect_exit(&message);
+    records.put(&old).expect_exit(&message);
+    records.commit().expect_exit(&message);
+    info!("{} superseded by {}", self.old, new["id"]);
+  }
+
+  fn successor_id(&self, records: &Records) -> Result<i64, String> {
+    if let Some(id) = self.id {
+      if records.contains(id)? {
+        return Err(format!("{} already exists", id));
+      }
+      return Ok(id);
+    }
+    let mut provider = FileIdProvider::new(&self.id_file);
+    loop {
+      let id = provider.next_id()?;
+      if !records.contains(id)? {
+        return Ok(id);
+      }
+      info!("{} already exists, allocating another id", id);
+    }
+  }
+}
+
+fn today() -> String {
+  chrono::Local::now().format("%Y-%m-%d").to_string()
+}
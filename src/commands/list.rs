@@ -1,9 +1,12 @@
-use crate::expression::{Evaluate, Predicate};
+use crate::expression::{parse_projection, Evaluate, Predicate};
 use crate::repo::Walk;
 use crate::sqlite;
+use crate::std::StringifyError;
 use crate::utils::ResultExit;
+use crate::JsonValue;
+use clap::builder::PossibleValuesParser;
 use clap::Parser;
-use log::error;
+use log::{error, info};
 use std::convert::TryFrom;
 use std::io::{Read, Write};
 use std::path::Path;
@@ -25,32 +28,70 @@ pub struct List {
   /// Filter lister geojson with expression.
   #[arg(long = "filter")]
   pub filter: Option<String>,
+  /// Print only these properties or expressions, separated by commas: `wof:id,wof:hierarchy[0].country_id`.
+  #[arg(long = "select")]
+  pub select: Option<String>,
+  /// Output format of the selected properties.
+  #[arg(
+    long = "format",
+    default_value = "csv",
+    value_parser = PossibleValuesParser::new(&["csv", "tsv", "jsonl"]),
+  )]
+  pub format: String,
+  /// Don't print the header line of the csv and tsv formats.
+  #[arg(long = "no-header")]
+  pub no_header: bool,
+  /// Activate verbose mode, shows the SQL query used on databases.
+  #[arg(short = 'v', long = "verbose")]
+  pub verbose: bool,
 }
 
 impl List {
   pub fn exec(&self) {
+    crate::utils::logger::set_verbose(self.verbose, "wof::list").expect_exit("Can't init logger.");
     let predicate: Predicate = if let Some(predicate) = &self.filter {
-      if !self.print_geojson {
-        error!("When --filter is used, you must also use --print-geojson");
+      if !self.print_geojson && self.select.is_none() {
+        error!("When --filter is used, you must also use --print-geojson or --select");
         std::process::exit(1);
       }
       Predicate::try_from(predicate.clone()).expect_exit("Inccorect expression")
     } else {
       Predicate::Boolean(true)
     };
+    let projection = if let Some(select) = &self.select {
+      parse_projection(select.clone()).expect_exit("Incorrect selection")
+    } else {
+      vec![]
+    };
+    if !projection.is_empty() && !self.no_header && self.format != "jsonl" {
+      let names: Vec<String> = projection
+        .iter()
+        .map(|(name, _)| format_cell(&JsonValue::from(name.as_str()), &self.format))
+        .collect();
+      writeln!(std::io::stdout(), "{}", names.join(separator(&self.format))).exit_silently();
+    }
     for directory in &self.directories {
       if Path::new(directory).is_dir() {
-        self.walk_directory(directory, &predicate)
+        self.walk_directory(directory, &predicate, &projection)
       } else {
-        self.list_sqlite(directory, &predicate)
+        self.list_sqlite(directory, &predicate, &projection)
       }
     }
   }
 
-  pub fn walk_directory(&self, directory: &String, predicate: &Predicate) {
+  fn print_documents(&self) -> bool {
+    self.print_geojson || self.select.is_some()
+  }
+
+  pub fn walk_directory(
+    &self,
+    directory: &String,
+    predicate: &Predicate,
+    projection: &[(String, Predicate)],
+  ) {
     for entry in Walk::new(directory.to_string(), self.alt, !self.no_deprecated) {
       if let Ok(path) = entry {
-        if self.print_geojson {
+        if self.print_documents() {
           let mut file = std::fs::File::open(path.path()).exit_silently();
           let mut buffer = String::new();
           file.read_to_string(&mut buffer).exit_silently();
@@ -59,8 +100,9 @@ impl List {
             .eval(&predicate)
             .expect_exit("Can't evaluate expression")
           {
-            crate::ser::json_to_writer(&json, &mut std::io::stdout()).exit_silently();
-            writeln!(std::io::stdout(), "").exit_silently()
+            self
+              .print_document(&json, projection)
+              .expect_exit("Can't evaluate selection")
           }
         } else {
           writeln!(std::io::stdout(), "{}", path.path().display()).exit_silently();
@@ -69,7 +111,12 @@ impl List {
     }
   }
 
-  pub fn list_sqlite(&self, directory: &String, predicate: &Predicate) {
+  pub fn list_sqlite(
+    &self,
+    directory: &String,
+    predicate: &Predicate,
+    projection: &[(String, Predicate)],
+  ) {
     let sqlite = sqlite::SQLite::new(
       directory,
       sqlite::SQLiteOpts {
@@ -81,14 +128,119 @@ impl List {
     )
     .expect_exit("Can't open the database");
 
-    if self.print_geojson {
-      sqlite
-        .write_all_geojsons(&mut std::io::stdout(), &predicate)
-        .expect_exit("Can't write to stdout");
+    if self.print_documents() {
+      if self.verbose {
+        let (sql, filter) = sqlite
+          .geojsons_query(predicate)
+          .expect_exit("Can't build the query");
+        info!("SQL query: {}", sql);
+        info!("SQL parameters: {:?}", filter.params);
+        info!("Evaluated on each document: {:?}", filter.remainder);
+      }
+      if projection.is_empty() {
+        sqlite
+          .write_all_geojsons(&mut std::io::stdout(), predicate)
+          .expect_exit("Can't write to stdout");
+      } else {
+        sqlite
+          .for_each_geojson(predicate, |json| self.print_document(json, projection))
+          .expect_exit("Can't write to stdout");
+      }
     } else {
       sqlite
         .write_all_ids(&mut std::io::stdout())
         .expect_exit("Can't write to stdout");
     }
   }
+
+  /// Print the minified document, or the selected values when there is a projection.
+  fn print_document(
+    &self,
+    json: &JsonValue,
+    projection: &[(String, Predicate)],
+  ) -> Result<(), String> {
+    let mut stdout = std::io::stdout();
+    if projection.is_empty() {
+      crate::ser::json_to_writer(json, &mut stdout).stringify_err("Can't write to output")?;
+      return writeln!(stdout).stringify_err("Can't write to output");
+    }
+    let mut values: Vec<(&str, JsonValue)> = vec![];
+    for (name, predicate) in projection {
+      values.push((name, json.eval(predicate)?.to_json()));
+    }
+    writeln!(stdout, "{}", format_row(&values, &self.format)).stringify_err("Can't write to output")
+  }
+}
+
+fn separator(format: &str) -> &'static str {
+  if format == "tsv" {
+    "\t"
+  } else {
+    ","
+  }
+}
+
+/// Format the selected values as a csv or tsv line, or as a json object for jsonl.
+fn format_row(values: &[(&str, JsonValue)], format: &str) -> String {
+  if format == "jsonl" {
+    let mut object = JsonValue::new_object();
+    for (name, value) in values {
+      object[*name] = value.clone();
+    }
+    return object.dump();
+  }
+  values
+    .iter()
+    .map(|(_, value)| format_cell(value, format))
+    .collect::<Vec<String>>()
+    .join(separator(format))
+}
+
+/// Strings are printed as is, null is empty and other values are printed as json.
+pub(super) fn format_cell(value: &JsonValue, format: &str) -> String {
+  let text = match value {
+    JsonValue::Null => String::new(),
+    JsonValue::String(_) | JsonValue::Short(_) => value.as_str().unwrap_or("").to_string(),
+    value => value.dump(),
+  };
+  if format == "tsv" {
+    text
+      .replace('\\', "\\\\")
+      .replace('\t', "\\t")
+      .replace('\n', "\\n")
+      .replace('\r', "\\r")
+  } else if text.contains([',', '"', '\n', '\r']) {
+    format!("\"{}\"", text.replace('"', "\"\""))
+  } else {
+    text
+  }
+}
+
+#[cfg(test)]
+mod test_list {
+  use super::*;
+  use json::array;
+
+  #[test]
+  fn format() {
+    let values = vec![
+      ("wof:id", JsonValue::from(101748927)),
+      ("wof:name", JsonValue::from("Ajaccio, \"Aiacciu\"")),
+      ("wof:lang", array!["fre", "cos"]),
+      ("geom:src", JsonValue::Null),
+      ("wof:label", JsonValue::from("a\tb")),
+    ];
+    assert_eq!(
+      format_row(&values, "csv"),
+      "101748927,\"Ajaccio, \"\"Aiacciu\"\"\",\"[\"\"fre\"\",\"\"cos\"\"]\",,a\tb"
+    );
+    assert_eq!(
+      format_row(&values, "tsv"),
+      "101748927\tAjaccio, \"Aiacciu\"\t[\"fre\",\"cos\"]\t\ta\\tb"
+    );
+    assert_eq!(
+      format_row(&values, "jsonl"),
+      "{\"wof:id\":101748927,\"wof:name\":\"Ajaccio, \\\"Aiacciu\\\"\",\"wof:lang\":[\"fre\",\"cos\"],\"geom:src\":null,\"wof:label\":\"a\\tb\"}"
+    );
+  }
 }
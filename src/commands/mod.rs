@@ -1,11 +1,20 @@
 use crate::commands::build::Build;
 use crate::commands::completion::Completion;
+use crate::commands::create::Create;
+use crate::commands::diff::Diff;
+use crate::commands::export::Export;
 use crate::commands::fetch::Fetch;
 use crate::commands::fix::FixCommand;
 use crate::commands::git::Git;
+use crate::commands::hierarchy::Hierarchy;
+use crate::commands::integrity::Integrity;
+use crate::commands::lifecycle::{Deprecate, Supersede};
 use crate::commands::list::List;
 use crate::commands::patch::Patch;
+use crate::commands::pip::Pip;
 use crate::commands::print::Print;
+use crate::commands::search::Search;
+use crate::commands::validate::Validate;
 use crate::std::StringifyError;
 use crate::utils::ResultExit;
 use clap::Parser;
@@ -17,12 +26,21 @@ use tar::Archive;
 
 mod build;
 mod completion;
+mod create;
+mod diff;
+mod export;
 mod fetch;
 mod fix;
 mod git;
+mod hierarchy;
+mod integrity;
+mod lifecycle;
 mod list;
 mod patch;
+mod pip;
 mod print;
+mod search;
+mod validate;
 
 #[derive(Debug, Parser)]
 pub enum Command {
@@ -32,24 +50,54 @@ pub enum Command {
   /// Generate autocompletion file for your shell.
   #[command(name = "completion", subcommand)]
   Completion(Completion),
+  /// Create a new WOF document with an allocated id in its repository path.
+  #[command(name = "create")]
+  Create(Create),
+  /// Deprecate or cessate WOF documents, they are no longer current.
+  #[command(name = "deprecate")]
+  Deprecate(Deprecate),
+  /// Compare two WOF documents, repositories or databases property by property.
+  #[command(name = "diff")]
+  Diff(Diff),
+  /// Export WOF documents in place, recompute their properties and bump `wof:lastmodified`.
+  #[command(name = "export")]
+  Export(Export),
   /// Fetch WOF data from github.
   #[command(name = "fetch")]
   Fetch(Fetch),
   /// Git tools for the Who's On First repositories.
   #[command(name = "git")]
   Git(Git),
+  /// Rebuild `wof:parent_id`, `wof:hierarchy` and `wof:belongsto` from the polygons of the repositories.
+  #[command(name = "hierarchy")]
+  Hierarchy(Hierarchy),
   /// Patch WOF documents with json. Can be via stdin or cmd argument.
   #[command(name = "patch")]
   Patch(Patch),
+  /// Find the WOF polygons containing coordinates.
+  #[command(name = "pip")]
+  Pip(Pip),
   /// Print to stdout WOF document by id. Can be via stdin or cmd argument.
   #[command(name = "print")]
   Print(Print),
+  /// Search WOF documents by name in a SQLite database.
+  #[command(name = "search")]
+  Search(Search),
+  /// Supersede a WOF document by an existing or a cloned one, both documents are updated.
+  #[command(name = "supersede")]
+  Supersede(Supersede),
   /// List all WOF document in the directory.
   #[command(name = "list")]
   List(List),
   /// Fix WOF data with some custom rules.
   #[command(name = "fix")]
   Fix(FixCommand),
+  /// Check WOF documents properties, ids, paths, bbox and geometry hashes.
+  #[command(name = "validate")]
+  Validate(Validate),
+  /// Check references between documents: parents, ancestors, supersessions and placetypes.
+  #[command(name = "integrity")]
+  Integrity(Integrity),
 }
 
 impl Command {
@@ -57,12 +105,22 @@ impl Command {
     match self {
       Command::Git(executable) => executable.exec(),
       Command::Completion(executable) => executable.exec(),
+      Command::Create(executable) => executable.exec(),
+      Command::Deprecate(executable) => executable.exec(),
+      Command::Diff(executable) => executable.exec(),
+      Command::Export(executable) => executable.exec(),
       Command::Fetch(executable) => executable.exec(),
+      Command::Hierarchy(executable) => executable.exec(),
       Command::Patch(executable) => executable.exec(),
+      Command::Pip(executable) => executable.exec(),
       Command::Print(executable) => executable.exec(),
+      Command::Search(executable) => executable.exec(),
+      Command::Supersede(executable) => executable.exec(),
       Command::List(executable) => executable.exec(),
       Command::Build(executable) => executable.exec(),
       Command::Fix(executable) => executable.exec(),
+      Command::Validate(executable) => executable.exec(),
+      Command::Integrity(executable) => executable.exec(),
     }
   }
 
@@ -126,6 +184,27 @@ pub fn assert_directory_exists<P: AsRef<Path>>(path: P) {
   }
 }
 
+/// Write a document through a temporary file renamed over the original, a failed write leaves the
+/// original file as it was.
+pub fn write_wof_file(path: &Path, wof_obj: &crate::wof::WOFGeoJSON) -> Result<(), String> {
+  let file_name = path
+    .file_name()
+    .map(|name| name.to_string_lossy().to_string())
+    .unwrap_or_default();
+  let temporary_path = path.with_file_name(format!(".{}.tmp", file_name));
+  let written = std::fs::File::create(&temporary_path)
+    .stringify_err("Can't create the file")
+    .and_then(|mut file| {
+      crate::ser::wof_to_writer_pretty(wof_obj, &mut file).stringify_err("Can't write the file")?;
+      file.sync_all().stringify_err("Can't sync the file")
+    });
+  if written.is_err() {
+    let _ = std::fs::remove_file(&temporary_path);
+  }
+  written?;
+  std::fs::rename(&temporary_path, path).stringify_err("Can't rename the temporary file")
+}
+
 pub fn download_tar_gz_strip<P: AsRef<Path>>(
   url: String,
   dest: P,
@@ -24,6 +24,9 @@ pub struct Patch {
   /// Continue on data not found
   #[arg(long = "ignore-not-found", default_value = "false")]
   pub ignore_not_found: bool,
+  /// Patch the database with a WAL journal, one transaction per document.
+  #[arg(long = "safe")]
+  pub safe: bool,
 }
 
 impl Patch {
@@ -36,7 +39,17 @@ impl Patch {
         pretty: !self.no_pretty,
         ..SQLiteOpts::default()
       };
-      Some(SQLite::new(&self.original, sqlite_options).expect_exit("Can't open the database."))
+      let mut sqlite =
+        SQLite::new(&self.original, sqlite_options).expect_exit("Can't open the database.");
+      sqlite
+        .use_existing_tables()
+        .expect_exit("Can't read the database tables.");
+      if self.safe {
+        sqlite
+          .set_safe_mode()
+          .expect_exit("Can't enable the safe mode.");
+      }
+      Some(sqlite)
     };
 
     if let Some(ref patchfile) = self.patchfile {
@@ -50,7 +63,7 @@ impl Patch {
           .read_to_string(&mut buffer)
           .expect_exit(&format!("Can't open file {}", patchfile));
         self
-          .apply_buffer_patch(&buffer, &sqlite)
+          .apply_file_patch(&buffer, &sqlite)
           .expect_exit(&format!("Something goes wrong with patch {}", patchfile));
       };
     }
@@ -87,7 +100,7 @@ impl Patch {
           .read_to_string(&mut buffer)
           .expect_exit(&format!("Can't open file {:?}", path.path()));
         self
-          .apply_buffer_patch(&buffer, &sqlite)
+          .apply_file_patch(&buffer, &sqlite)
           .expect_exit(&format!(
             "Something goes wrong with patch {:?}",
             path.path()
@@ -96,6 +109,22 @@ impl Patch {
     }
   }
 
+  /// A file contains one JSON patch, or one patch per line like the output of `wof diff --format patch`.
+  fn apply_file_patch(&self, buffer: &String, sqlite: &Option<SQLite>) -> Result<(), String> {
+    if crate::parse_string_to_json(buffer).is_ok() {
+      return self.apply_buffer_patch(buffer, sqlite);
+    }
+    for (line, input) in buffer.lines().enumerate() {
+      let input = input.trim().to_string();
+      if !input.is_empty() && !input.starts_with("#") {
+        self
+          .apply_buffer_patch(&input, sqlite)
+          .map_err(|e| format!("line {}: {}", line + 1, e))?;
+      }
+    }
+    Ok(())
+  }
+
   fn apply_buffer_patch(&self, buffer: &String, sqlite: &Option<SQLite>) -> Result<(), String> {
     let json_value = crate::parse_string_to_json(buffer).stringify_err("Malformed json object")?;
     let json = json_value
@@ -122,8 +151,14 @@ impl Patch {
       Patch::apply_patch_to_original(&json, &mut original_json)
         .stringify_err(&format!("Can't apply patch on id {}", id))?;
       let wof = WOFGeoJSON::as_valid_wof_geojson(&original_json)?;
+      if self.safe {
+        sqlite.begin()?;
+      }
       sqlite.set_geojson_alt(wof.id, &original_source, 1)?;
       sqlite.add(wof)?;
+      if self.safe {
+        sqlite.commit()?;
+      }
     } else {
       let path = utils::get_geojson_path_from_id(&self.original, id)
         .ok_or(format!("GeoJSON {} not found in {}", id, self.original));
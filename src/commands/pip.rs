@@ -0,0 +1,139 @@
+use crate::commands::list::format_cell;
+use crate::pip::{parse_coordinate, PipIndex, PipResult};
+use crate::repo::Walk;
+use crate::sqlite::{SQLite, SQLiteOpts};
+use crate::utils::ResultExit;
+use crate::JsonValue;
+use clap::builder::PossibleValuesParser;
+use clap::Parser;
+use log::{info, warn};
+use std::io::{stdin, stdout, BufRead, BufReader, Write};
+use std::path::Path;
+
+#[derive(Debug, Parser)]
+pub struct Pip {
+  /// WOF repository or SQLite database with the polygons.
+  pub source: String,
+  /// Coordinates to look up, as `lon,lat`.
+  #[arg(allow_hyphen_values = true)]
+  pub coordinates: Vec<String>,
+  /// CSV file of points, the first two columns are the longitude and the latitude. Use `-` for stdin.
+  #[arg(long = "csv")]
+  pub csv: Option<String>,
+  /// Don't use deprecated features.
+  #[arg(long = "no-deprecated")]
+  pub no_deprecated: bool,
+  /// Output format of the results.
+  #[arg(
+    long = "format",
+    default_value = "csv",
+    value_parser = PossibleValuesParser::new(&["csv", "jsonl"]),
+  )]
+  pub format: String,
+  /// Activate verbose mode.
+  #[arg(short = 'v', long = "verbose")]
+  pub verbose: bool,
+}
+
+/// Repositories are loaded in memory, databases are queried for each point.
+enum Polygons {
+  Index(PipIndex),
+  SQLite(SQLite),
+}
+
+impl Polygons {
+  fn lookup(&self, lon: f64, lat: f64) -> Result<Vec<PipResult>, String> {
+    match self {
+      Polygons::Index(index) => Ok(index.lookup(lon, lat)),
+      Polygons::SQLite(sqlite) => {
+        let mut index = PipIndex::new();
+        for json in sqlite.get_geojsons_by_point(lon, lat)? {
+          index.add(&json)?;
+        }
+        Ok(index.lookup(lon, lat))
+      }
+    }
+  }
+}
+
+impl Pip {
+  pub fn exec(&self) {
+    crate::utils::logger::set_verbose(self.verbose, "wof::pip").expect_exit("Can't init logger.");
+    let polygons = self.load().expect_exit("Can't load the polygons");
+    if self.format == "csv" {
+      writeln!(stdout(), "lon,lat,id,placetype,name").exit_silently();
+    }
+    for coordinate in &self.coordinates {
+      let (lon, lat) = parse_coordinate(coordinate).expect_exit("Incorrect coordinate");
+      self.print_results(&polygons, lon, lat);
+    }
+    if let Some(csv) = &self.csv {
+      let reader: Box<dyn BufRead> = if csv == "-" {
+        Box::new(BufReader::new(stdin()))
+      } else {
+        Box::new(BufReader::new(
+          std::fs::File::open(csv).expect_exit(&format!("Can't open {}", csv)),
+        ))
+      };
+      for line in reader.lines() {
+        let line = line.expect_exit("Can't read the csv");
+        let columns: Vec<&str> = line.splitn(3, ',').take(2).collect();
+        // Lines that are not coordinates, like the header, are skipped.
+        if let Ok((lon, lat)) = parse_coordinate(&columns.join(",")) {
+          self.print_results(&polygons, lon, lat);
+        }
+      }
+    }
+  }
+
+  fn load(&self) -> Result<Polygons, String> {
+    if !Path::new(&self.source).is_dir() {
+      let opts = SQLiteOpts {
+        deprecated: !self.no_deprecated,
+        ..Default::default()
+      };
+      return Ok(Polygons::SQLite(SQLite::new(&self.source, opts)?));
+    }
+    let mut index = PipIndex::new();
+    for entry in Walk::new(&self.source, false, !self.no_deprecated) {
+      let entry = entry.map_err(|e| format!("Can't walk through {}: {}", self.source, e))?;
+      let json = crate::parse_file_to_json(entry.path())?;
+      index
+        .add(&json)
+        .map_err(|e| format!("{}: {}", entry.path().display(), e))?;
+    }
+    if index.is_empty() {
+      warn!("No polygons found in {}", self.source);
+    }
+    info!("{} polygons loaded", index.len());
+    Ok(Polygons::Index(index))
+  }
+
+  fn print_results(&self, polygons: &Polygons, lon: f64, lat: f64) {
+    let results = polygons
+      .lookup(lon, lat)
+      .expect_exit(&format!("Can't look up {},{}", lon, lat));
+    let mut stdout = stdout();
+    if self.format == "jsonl" {
+      let json = json::object! {
+        "lon" => lon,
+        "lat" => lat,
+        "results" => results.iter().map(PipResult::to_json).collect::<Vec<JsonValue>>(),
+      };
+      writeln!(stdout, "{}", json.dump()).exit_silently();
+      return;
+    }
+    for result in results {
+      writeln!(
+        stdout,
+        "{},{},{},{},{}",
+        lon,
+        lat,
+        result.id,
+        format_cell(&result.placetype.as_str().into(), "csv"),
+        format_cell(&result.name.as_str().into(), "csv")
+      )
+      .exit_silently();
+    }
+  }
+}
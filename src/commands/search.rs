@@ -0,0 +1,89 @@
+use crate::sqlite::{SQLite, SQLiteOpts};
+use crate::utils::ResultExit;
+use crate::JsonValue;
+use clap::builder::PossibleValuesParser;
+use clap::Parser;
+use std::io::{stdout, Write};
+
+#[derive(Debug, Parser)]
+pub struct Search {
+  /// Name to search, words are matched by prefix and diacritics are ignored.
+  pub query: String,
+  /// SQLite database built with `wof build sqlite --search`.
+  #[arg(long = "db", default_value = "whosonfirst-data-latest.db")]
+  pub db: String,
+  /// Return only documents with this placetype.
+  #[arg(long = "placetype")]
+  pub placetype: Option<String>,
+  /// Return only documents of this country.
+  #[arg(long = "country")]
+  pub country: Option<String>,
+  /// Maximum number of results.
+  #[arg(long = "limit", default_value = "10")]
+  pub limit: usize,
+  /// Output format of the results.
+  #[arg(
+    long = "format",
+    default_value = "table",
+    value_parser = PossibleValuesParser::new(&["table", "json"]),
+  )]
+  pub format: String,
+}
+
+const COLUMNS: [&str; 5] = ["id", "placetype", "country", "name", "parent_id"];
+
+impl Search {
+  pub fn exec(&self) {
+    crate::utils::logger::set_verbose(false, "wof::search").expect_exit("Can't init logger.");
+    let sqlite =
+      SQLite::new(&self.db, SQLiteOpts::default()).expect_exit("Can't open the database");
+    let results = sqlite
+      .search(
+        &self.query,
+        self.placetype.as_deref(),
+        self.country.as_deref(),
+        self.limit,
+      )
+      .expect_exit("Can't search in the database");
+    let mut stdout = stdout();
+    if self.format == "json" {
+      for row in &results {
+        writeln!(stdout, "{}", row.dump()).exit_silently();
+      }
+      return;
+    }
+    let rows: Vec<Vec<String>> = results
+      .iter()
+      .map(|row| COLUMNS.iter().map(|column| cell(&row[*column])).collect())
+      .collect();
+    let widths: Vec<usize> = COLUMNS
+      .iter()
+      .enumerate()
+      .map(|(i, column)| {
+        rows
+          .iter()
+          .map(|row| row[i].chars().count())
+          .chain([column.len()])
+          .max()
+          .unwrap_or(0)
+      })
+      .collect();
+    let header: Vec<String> = COLUMNS.iter().map(|column| column.to_string()).collect();
+    for row in [header].iter().chain(rows.iter()) {
+      let line: Vec<String> = row
+        .iter()
+        .zip(widths.iter())
+        .map(|(value, width)| format!("{:width$}", value, width = width))
+        .collect();
+      writeln!(stdout, "{}", line.join("  ").trim_end()).exit_silently();
+    }
+  }
+}
+
+fn cell(value: &JsonValue) -> String {
+  match value {
+    JsonValue::Null => String::new(),
+    JsonValue::String(_) | JsonValue::Short(_) => value.as_str().unwrap_or("").to_string(),
+    value => value.dump(),
+  }
+}
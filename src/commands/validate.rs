@@ -0,0 +1,110 @@
+use crate::repo::Walk;
+use crate::utils::ResultExit;
+use crate::validate::{validate, ValidationError};
+use clap::builder::PossibleValuesParser;
+use clap::Parser;
+use std::io::{stdin, Write};
+use std::path::Path;
+
+#[derive(Debug, Parser)]
+pub struct Validate {
+  /// Paths to WOF documents or directories. Documents can also be sent via stdin.
+  #[arg(default_value = ".")]
+  pub paths: Vec<String>,
+  /// Output format of the report.
+  #[arg(
+    long = "format",
+    default_value = "text",
+    value_parser = PossibleValuesParser::new(&["text", "json"]),
+  )]
+  pub format: String,
+  /// Also report valid documents.
+  #[arg(short = 'v', long = "verbose")]
+  pub verbose: bool,
+}
+
+impl Validate {
+  pub fn exec(&self) {
+    crate::utils::logger::set_verbose(false, "wof::validate").expect_exit("Can't init logger.");
+    let mut invalid = 0;
+    if crate::commands::input_pipe() {
+      let mut line = 0;
+      loop {
+        let mut input = String::new();
+        match stdin().read_line(&mut input) {
+          Ok(0) => break,
+          Ok(_) => {
+            line += 1;
+            if input.trim().is_empty() {
+              continue;
+            }
+            let errors = match crate::parse_string_to_json(&input) {
+              Ok(json) => validate(&json, None),
+              Err(e) => vec![ValidationError {
+                rule: "json",
+                message: e,
+              }],
+            };
+            invalid += self.report(&format!("stdin:{}", line), &errors);
+          }
+          Err(_) => break,
+        }
+      }
+    } else {
+      for path in &self.paths {
+        for entry in Walk::new(path, true, true) {
+          match entry {
+            Ok(entry) => invalid += self.validate_file(entry.path()),
+            Err(e) => {
+              invalid += self.report(
+                path,
+                &[ValidationError {
+                  rule: "io",
+                  message: e.to_string(),
+                }],
+              )
+            }
+          }
+        }
+      }
+    }
+    if invalid > 0 {
+      std::process::exit(1);
+    }
+  }
+
+  fn validate_file(&self, path: &Path) -> usize {
+    let errors = match crate::parse_file_to_json(path) {
+      Ok(json) => validate(&json, Some(path)),
+      Err(e) => vec![ValidationError {
+        rule: "json",
+        message: e,
+      }],
+    };
+    self.report(&path.display().to_string(), &errors)
+  }
+
+  /// Print the report of a document and returns 1 when it is invalid.
+  fn report(&self, name: &str, errors: &[ValidationError]) -> usize {
+    if errors.is_empty() && !self.verbose {
+      return 0;
+    }
+    let mut stdout = std::io::stdout();
+    if self.format == "json" {
+      let report = json::object! {
+        "path" => name,
+        "valid" => errors.is_empty(),
+        "errors" => errors.iter().map(ValidationError::to_json).collect::<Vec<_>>(),
+      };
+      writeln!(stdout, "{}", report.dump()).exit_silently();
+    } else if errors.is_empty() {
+      writeln!(stdout, "{}: OK", name).exit_silently();
+    } else {
+      writeln!(stdout, "{}:", name).exit_silently();
+      for error in errors {
+        writeln!(stdout, "  [{}] {}", error.rule, error.message).exit_silently();
+      }
+    }
+    usize::from(!errors.is_empty())
+  }
+}
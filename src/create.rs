@@ -0,0 +1,156 @@
+//! Mint new WOF documents with ids allocated by an [`IdProvider`].
+use crate::export::{export_json_value_with_options, ExportOptions};
+use crate::placetypes::get_rank;
+use crate::std::StringifyError;
+use crate::utils::JsonUtils;
+use crate::JsonValue;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Source of the ids of new documents.
+pub trait IdProvider {
+  /// Returns a new id, an id is never returned twice.
+  fn next_id(&mut self) -> Result<i64, String>;
+}
+
+/// Allocate ids from a range, the last allocated id is saved in a file so it works offline.
+#[derive(Debug, Clone)]
+pub struct FileIdProvider {
+  path: PathBuf,
+  start: i64,
+  end: i64,
+}
+
+impl FileIdProvider {
+  /// First id of the default range, above the ids of the WOF datasets.
+  pub const DEFAULT_START: i64 = 2_000_000_000;
+  /// Largest id that can be allocated, [`crate::WOFGeoJSON`] reads the top level `id` as an `i32`
+  /// so every command reading the new document would fail on a larger id.
+  pub const MAX_ID: i64 = i32::MAX as i64;
+
+  pub fn new<P: AsRef<Path>>(path: P) -> Self {
+    FileIdProvider {
+      path: path.as_ref().to_path_buf(),
+      start: Self::DEFAULT_START,
+      end: Self::MAX_ID,
+    }
+  }
+
+  /// Allocate ids between `start` and `end`, both included.
+  pub fn with_range<P: AsRef<Path>>(path: P, start: i64, end: i64) -> Result<Self, String> {
+    if start <= 0 || start > end || end > Self::MAX_ID {
+      return Err(format!("{}-{} is not a valid range of ids", start, end));
+    }
+    Ok(FileIdProvider {
+      path: path.as_ref().to_path_buf(),
+      start,
+      end,
+    })
+  }
+
+  fn last_id(&self) -> Result<Option<i64>, String> {
+    if !self.path.exists() {
+      return Ok(None);
+    }
+    let content = fs::read_to_string(&self.path).stringify_err("Can't read the id file")?;
+    content
+      .trim()
+      .parse::<i64>()
+      .map(Some)
+      .map_err(|_| format!("`{}` is not an id", content.trim()))
+  }
+}
+
+impl IdProvider for FileIdProvider {
+  fn next_id(&mut self) -> Result<i64, String> {
+    let id = match self.last_id()? {
+      Some(last_id) if last_id >= self.start => last_id + 1,
+      _ => self.start,
+    };
+    if id > self.end {
+      return Err(format!(
+        "All ids between {} and {} are allocated",
+        self.start, self.end
+      ));
+    }
+    fs::write(&self.path, format!("{}\n", id)).stringify_err("Can't write the id file")?;
+    Ok(id)
+  }
+}
+
+/// Check a minimal GeoJSON Feature and export it, `wof:name` and `wof:placetype` are required. The
+/// id of the document is set by [`set_document_id`], so no id is allocated for an invalid document.
+pub fn prepare_document(json: &JsonValue) -> Result<JsonValue, String> {
+  json.assert_is_object()?;
+  let mut json = json.clone();
+  if json["properties"].is_null() {
+    json["properties"] = JsonValue::new_object();
+  }
+  json["properties"].assert_is_object()?;
+  if json["properties"]["wof:name"]
+    .as_str()
+    .is_none_or(str::is_empty)
+  {
+    return Err("`wof:name` is required".to_string());
+  }
+  match json["properties"]["wof:placetype"].as_str() {
+    Some(placetype) if get_rank(placetype).is_some() => (),
+    Some(placetype) => return Err(format!("`{}` is not a placetype", placetype)),
+    None => return Err("`wof:placetype` is required".to_string()),
+  }
+  json["type"] = "Feature".into();
+  json["properties"]["wof:id"] = 0.into();
+  export_json_value_with_options(&json, &ExportOptions::default())
+}
+
+/// Set the id of a document returned by [`prepare_document`].
+pub fn set_document_id(document: &mut JsonValue, id: i64) {
+  document["id"] = id.into();
+  document["properties"]["wof:id"] = id.into();
+}
+
+#[cfg(test)]
+mod test_create {
+  use super::*;
+  use json::object;
+
+  #[test]
+  fn file_id_provider() {
+    let path = std::env::temp_dir().join(format!("wof-id-{}", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut provider = FileIdProvider::with_range(&path, 10, 11).unwrap();
+    assert_eq!(provider.next_id(), Ok(10));
+    let mut provider = FileIdProvider::with_range(&path, 10, 11).unwrap();
+    assert_eq!(provider.next_id(), Ok(11));
+    assert!(provider.next_id().is_err());
+    assert_eq!(
+      FileIdProvider::new(&path).next_id(),
+      Ok(FileIdProvider::DEFAULT_START)
+    );
+    assert!(FileIdProvider::with_range(&path, 11, 10).is_err());
+    assert!(FileIdProvider::with_range(&path, 1, 10_000_000_000).is_err());
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn document() {
+    let json = object! {
+      "properties" => object!{ "wof:name" => "Null Island", "wof:placetype" => "locality" },
+      "geometry" => object!{ "coordinates" => vec![1.0, 2.0], "type" => "Point" },
+    };
+    let mut document = prepare_document(&json).unwrap();
+    set_document_id(&mut document, 42);
+    assert_eq!(document["id"], 42);
+    assert_eq!(document["type"], "Feature");
+    assert_eq!(document["properties"]["wof:id"], 42);
+    assert_eq!(document["properties"]["wof:parent_id"], -1);
+    assert_eq!(document["bbox"], json::array![1.0, 2.0, 1.0, 2.0]);
+
+    let mut unknown = json.clone();
+    unknown["properties"]["wof:placetype"] = "town".into();
+    assert!(prepare_document(&unknown).is_err());
+    let mut unnamed = json.clone();
+    unnamed["properties"].remove("wof:name");
+    assert!(prepare_document(&unnamed).is_err());
+  }
+}
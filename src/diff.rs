@@ -0,0 +1,227 @@
+//! Compare two versions of a WOF document property by property.
+use crate::utils::compute::compute_distance_km;
+use crate::utils::{GeoCompute, JsonUtils};
+use crate::JsonValue;
+
+/// Summary of the changes of a geometry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeometryDiff {
+  /// Number of vertices before and after.
+  pub vertices: (usize, usize),
+  /// Difference of area in square meters, positive when the geometry grows.
+  pub area_delta_m: f64,
+  /// Distance in kilometers between the two centroids.
+  pub centroid_shift_km: f64,
+}
+
+/// All differences between two versions of a document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentDiff {
+  pub id: i64,
+  /// Properties only in the new version.
+  pub added: Vec<(String, JsonValue)>,
+  /// Properties only in the old version.
+  pub removed: Vec<String>,
+  /// Properties in both versions with their old and new values.
+  pub changed: Vec<(String, JsonValue, JsonValue)>,
+  /// Old and new `bbox` when they are not the same.
+  pub bbox: Option<(JsonValue, JsonValue)>,
+  /// Set when the geometry is not the same.
+  pub geometry: Option<GeometryDiff>,
+  after_geometry: JsonValue,
+}
+
+impl DocumentDiff {
+  pub fn is_empty(&self) -> bool {
+    self.added.is_empty()
+      && self.removed.is_empty()
+      && self.changed.is_empty()
+      && self.bbox.is_none()
+      && self.geometry.is_none()
+  }
+
+  /// The patch applying this diff with `wof patch`, removed properties are set to `null`.
+  pub fn to_patch(&self) -> JsonValue {
+    let mut properties = JsonValue::new_object();
+    for (key, value) in &self.added {
+      properties[key.as_str()] = value.clone();
+    }
+    for key in &self.removed {
+      properties[key.as_str()] = JsonValue::Null;
+    }
+    for (key, _, value) in &self.changed {
+      properties[key.as_str()] = value.clone();
+    }
+    let mut patch = json::object! { "id" => self.id };
+    if !properties.is_empty() {
+      patch["properties"] = properties;
+    }
+    if self.geometry.is_some() {
+      patch["geometry"] = self.after_geometry.clone();
+    }
+    if let Some((_, bbox)) = &self.bbox {
+      patch["bbox"] = bbox.clone();
+    }
+    patch
+  }
+
+  pub fn to_json(&self) -> JsonValue {
+    let mut json = json::object! {
+      "id" => self.id,
+      "added" => JsonValue::new_object(),
+      "removed" => self.removed.clone(),
+      "changed" => JsonValue::new_object(),
+      "bbox" => JsonValue::Null,
+      "geometry" => JsonValue::Null,
+    };
+    for (key, value) in &self.added {
+      json["added"][key.as_str()] = value.clone();
+    }
+    for (key, before, after) in &self.changed {
+      json["changed"][key.as_str()] = json::array![before.clone(), after.clone()];
+    }
+    if let Some((before, after)) = &self.bbox {
+      json["bbox"] = json::array![before.clone(), after.clone()];
+    }
+    if let Some(geometry) = &self.geometry {
+      json["geometry"] = json::object! {
+        "vertices" => json::array![geometry.vertices.0, geometry.vertices.1],
+        "area_delta_m" => geometry.area_delta_m,
+        "centroid_shift_km" => geometry.centroid_shift_km,
+      };
+    }
+    json
+  }
+}
+
+/// Compare two versions of a document, the id is the one of the new version.
+pub fn diff_documents(before: &JsonValue, after: &JsonValue) -> Result<DocumentDiff, String> {
+  before.assert_is_object()?;
+  after.assert_is_object()?;
+  let id = after["id"]
+    .as_i64()
+    .or_else(|| before["id"].as_i64())
+    .ok_or("Top level `id` is not a number")?;
+  let mut diff = DocumentDiff {
+    id,
+    added: vec![],
+    removed: vec![],
+    changed: vec![],
+    bbox: None,
+    geometry: None,
+    after_geometry: after["geometry"].clone(),
+  };
+  let before_properties = &before["properties"];
+  let after_properties = &after["properties"];
+  for (key, value) in before_properties.entries() {
+    if !after_properties.has_key(key) {
+      diff.removed.push(key.to_string());
+    } else if after_properties[key] != *value {
+      diff.changed.push((
+        key.to_string(),
+        value.clone(),
+        after_properties[key].clone(),
+      ));
+    }
+  }
+  for (key, value) in after_properties.entries() {
+    if !before_properties.has_key(key) {
+      diff.added.push((key.to_string(), value.clone()));
+    }
+  }
+  if before["bbox"] != after["bbox"] {
+    diff.bbox = Some((before["bbox"].clone(), after["bbox"].clone()));
+  }
+  if before["geometry"] != after["geometry"] {
+    diff.geometry = Some(diff_geometries(&before["geometry"], &after["geometry"]));
+  }
+  Ok(diff)
+}
+
+fn diff_geometries(before: &JsonValue, after: &JsonValue) -> GeometryDiff {
+  let vertices = (
+    count_vertices(&before["coordinates"]),
+    count_vertices(&after["coordinates"]),
+  );
+  match (before.as_object(), after.as_object()) {
+    (Some(before), Some(after)) => GeometryDiff {
+      vertices,
+      area_delta_m: after.compute_area_m() - before.compute_area_m(),
+      centroid_shift_km: compute_distance_km(before.compute_centroid(), after.compute_centroid()),
+    },
+    _ => GeometryDiff {
+      vertices,
+      area_delta_m: 0.0,
+      centroid_shift_km: 0.0,
+    },
+  }
+}
+
+/// A vertex is an array of numbers, all other arrays are containers of vertices.
+fn count_vertices(coordinates: &JsonValue) -> usize {
+  match coordinates {
+    JsonValue::Array(members) if members.first().is_some_and(JsonValue::is_number) => 1,
+    JsonValue::Array(members) => members.iter().map(count_vertices).sum(),
+    _ => 0,
+  }
+}
+
+#[cfg(test)]
+mod test_diff {
+  use super::*;
+  use json::object;
+
+  fn document() -> JsonValue {
+    object! {
+      "id" => 0,
+      "type" => "Feature",
+      "properties" => object!{ "wof:id" => 0, "wof:name" => "Null Island", "wof:tags" => vec!["null"] },
+      "geometry" => object!{ "coordinates" => vec![0.0, 0.0], "type" => "Point" },
+      "bbox" => vec![0.0, 0.0, 0.0, 0.0],
+    }
+  }
+
+  #[test]
+  fn same_documents() {
+    let diff = diff_documents(&document(), &document()).unwrap();
+    assert!(diff.is_empty());
+    assert_eq!(diff.to_patch(), object! { "id" => 0 });
+  }
+
+  #[test]
+  fn changed_documents() {
+    let before = document();
+    let mut after = document();
+    after["properties"]["wof:name"] = "Null".into();
+    after["properties"]["wof:lang"] = json::array!["eng"];
+    after["properties"].remove("wof:tags");
+    after["geometry"] = object! {
+      "coordinates" => vec![vec![vec![0.0, 0.0], vec![1.0, 0.0], vec![1.0, 1.0], vec![0.0, 0.0]]],
+      "type" => "Polygon"
+    };
+    after["bbox"] = json::array![0.0, 0.0, 1.0, 1.0];
+    let diff = diff_documents(&before, &after).unwrap();
+    assert_eq!(
+      diff.added,
+      vec![("wof:lang".to_string(), json::array!["eng"])]
+    );
+    assert_eq!(diff.removed, vec!["wof:tags".to_string()]);
+    assert_eq!(
+      diff.changed,
+      vec![("wof:name".to_string(), "Null Island".into(), "Null".into())]
+    );
+    let geometry = diff.geometry.clone().unwrap();
+    assert_eq!(geometry.vertices, (1, 4));
+    assert!(geometry.area_delta_m > 0.0);
+    assert!(geometry.centroid_shift_km > 0.0);
+    assert_eq!(
+      diff.to_patch(),
+      object! {
+        "id" => 0,
+        "properties" => object!{ "wof:lang" => vec!["eng"], "wof:tags" => JsonValue::Null, "wof:name" => "Null" },
+        "geometry" => after["geometry"].clone(),
+        "bbox" => after["bbox"].clone(),
+      }
+    );
+  }
+}
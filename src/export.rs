@@ -1,12 +1,58 @@
 use crate::utils::{GeoCompute, GeoJsonUtils, JsonUtils};
+use crate::wof::EDTF_UNKNOWN;
 use json::object::Object;
 use json::{array, JsonValue};
+use std::time::{SystemTime, UNIX_EPOCH};
 
+/// Rules applied by [`export_json_value_with_options`], all of them are enabled by default.
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+  /// If true, will add missing properties with their default value, geometry properties included.
+  pub defaults: bool,
+  /// If true, will recompute `geom:*` properties and `wof:geomhash` when the geometry changed.
+  pub geometry: bool,
+  /// If true, will derive `wof:belongsto` from `wof:hierarchy`.
+  pub belongsto: bool,
+  /// If true, will set `mz:is_current` from the EDTF dates and the supersession.
+  pub is_current: bool,
+  /// If true, will set `wof:lastmodified` to now.
+  pub lastmodified: bool,
+}
+
+impl Default for ExportOptions {
+  fn default() -> Self {
+    ExportOptions {
+      defaults: true,
+      geometry: true,
+      belongsto: true,
+      is_current: true,
+      lastmodified: true,
+    }
+  }
+}
+
+/// Only add the missing properties, nothing already set is recomputed.
 pub fn export_json_value(json: &JsonValue) -> Result<JsonValue, String> {
+  export_json_value_with_options(
+    json,
+    &ExportOptions {
+      defaults: true,
+      geometry: false,
+      belongsto: false,
+      is_current: false,
+      lastmodified: false,
+    },
+  )
+}
+
+pub fn export_json_value_with_options(
+  json: &JsonValue,
+  options: &ExportOptions,
+) -> Result<JsonValue, String> {
   json.assert_is_object()?;
   let geometry = export_geometry(&json)?;
-  let properties = export_porperties(&json, &geometry.as_object().unwrap())?;
   let id = export_id(&json)?;
+  let properties = export_porperties(json, geometry.as_object().unwrap(), id, options)?;
   let bbox = export_bbox(&json)?;
 
   Ok(json::object! {
@@ -18,7 +64,12 @@ pub fn export_json_value(json: &JsonValue) -> Result<JsonValue, String> {
   })
 }
 
-fn export_porperties(json: &JsonValue, geometry: &Object) -> Result<JsonValue, String> {
+fn export_porperties(
+  json: &JsonValue,
+  geometry: &Object,
+  id: i64,
+  options: &ExportOptions,
+) -> Result<JsonValue, String> {
   let obj = json
     .as_object()
     .unwrap()
@@ -30,6 +81,32 @@ fn export_porperties(json: &JsonValue, geometry: &Object) -> Result<JsonValue, S
     .ok_or("`properties` key must be an object")?
     .clone();
 
+  if options.defaults {
+    export_defaults(&mut properties);
+  }
+  if options.geometry {
+    remove_stale_geometry_properties(&mut properties, geometry);
+  }
+  if options.defaults || options.geometry {
+    export_geometry_properties(&mut properties, geometry);
+  }
+  if options.belongsto {
+    export_belongsto(&mut properties, id);
+  }
+  if options.is_current {
+    export_is_current(&mut properties);
+  }
+  if options.lastmodified {
+    let now = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map_err(|e| format!("Can't get the current time: {}", e))?;
+    properties.insert("wof:lastmodified", JsonValue::from(now.as_secs()));
+  }
+
+  Ok(JsonValue::Object(properties))
+}
+
+fn export_defaults(properties: &mut Object) {
   if properties.get("edtf:cessation").is_none() {
     properties.insert("edtf:cessation", JsonValue::from("uuuu"));
   }
@@ -72,6 +149,32 @@ fn export_porperties(json: &JsonValue, geometry: &Object) -> Result<JsonValue, S
   if properties.get("wof:tags").is_none() {
     properties.insert("wof:tags", array![]);
   }
+}
+
+/// Geometry properties are removed when `wof:geomhash` is not the hash of the geometry, so they
+/// are computed again.
+fn remove_stale_geometry_properties(properties: &mut Object, geometry: &Object) {
+  let geomhash = geometry.compute_md5();
+  if properties
+    .get("wof:geomhash")
+    .and_then(|hash| hash.as_str())
+    != Some(geomhash.as_str())
+  {
+    for key in [
+      "geom:area",
+      "geom:area_square_m",
+      "geom:bbox",
+      "geom:latitude",
+      "geom:longitude",
+      "wof:geomhash",
+    ] {
+      properties.remove(key);
+    }
+  }
+}
+
+/// Missing geometry properties are computed from the geometry.
+fn export_geometry_properties(properties: &mut Object, geometry: &Object) {
   if properties.get("geom:area").is_none() {
     properties.insert("geom:area", JsonValue::from(geometry.compute_area()));
   }
@@ -94,8 +197,42 @@ fn export_porperties(json: &JsonValue, geometry: &Object) -> Result<JsonValue, S
   if properties.get("wof:geomhash").is_none() {
     properties.insert("wof:geomhash", JsonValue::from(geometry.compute_md5()));
   }
+}
 
-  Ok(JsonValue::Object(properties))
+/// `wof:belongsto` contains all ancestors of all hierarchies in their order, without the document itself.
+pub(crate) fn export_belongsto(properties: &mut Object, id: i64) {
+  let mut belongsto: Vec<i64> = vec![];
+  if let Some(hierarchies) = properties.get("wof:hierarchy") {
+    let ancestors = hierarchies
+      .members()
+      .flat_map(|hierarchy| hierarchy.entries())
+      .filter(|(key, _)| key.ends_with("_id"))
+      .filter_map(|(_, ancestor_id)| ancestor_id.as_i64());
+    for ancestor_id in ancestors {
+      if ancestor_id > 0 && ancestor_id != id && !belongsto.contains(&ancestor_id) {
+        belongsto.push(ancestor_id);
+      }
+    }
+  }
+  properties.insert("wof:belongsto", JsonValue::from(belongsto));
+}
+
+/// A document is not current when it's deprecated, ceased or superseded. Otherwise the value is kept.
+fn export_is_current(properties: &mut Object) {
+  let is_known_date = |key: &str| {
+    properties
+      .get(key)
+      .and_then(|date| date.as_str())
+      .is_some_and(|date| !EDTF_UNKNOWN.contains(&date))
+  };
+  let is_superseded = properties
+    .get("wof:superseded_by")
+    .is_some_and(|ids| !ids.is_empty());
+  if is_known_date("edtf:deprecated") || is_known_date("edtf:cessation") || is_superseded {
+    properties.insert("mz:is_current", JsonValue::from(0));
+  } else if properties.get("mz:is_current").is_none() {
+    properties.insert("mz:is_current", JsonValue::from(-1));
+  }
 }
 
 fn export_geometry(json: &JsonValue) -> Result<JsonValue, String> {
@@ -172,3 +309,152 @@ fn export_bbox(json: &JsonValue) -> Result<Vec<f64>, String> {
   let geom = json.as_object().unwrap().get("geometry").unwrap();
   Ok(geom.as_object().unwrap().compute_bbox())
 }
+
+/// A difference between a document and its export.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+  Added(String),
+  Removed(String),
+  Modified(String),
+}
+
+impl std::fmt::Display for Change {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Change::Added(key) => write!(f, "+ {}", key),
+      Change::Removed(key) => write!(f, "- {}", key),
+      Change::Modified(key) => write!(f, "~ {}", key),
+    }
+  }
+}
+
+/// Returns the changes of the `geometry`, the `bbox` and each property between two documents.
+/// `wof:lastmodified` is ignored.
+pub fn export_changes(before: &JsonValue, after: &JsonValue) -> Vec<Change> {
+  let mut changes: Vec<Change> = vec![];
+  for key in ["geometry", "bbox"] {
+    if before[key] != after[key] {
+      changes.push(Change::Modified(key.to_string()));
+    }
+  }
+  let before = &before["properties"];
+  let after = &after["properties"];
+  let mut keys: Vec<&str> = before
+    .entries()
+    .chain(after.entries())
+    .map(|(key, _)| key)
+    .filter(|key| *key != "wof:lastmodified")
+    .collect();
+  keys.sort_unstable();
+  keys.dedup();
+  for key in keys {
+    match (before.has_key(key), after.has_key(key)) {
+      (false, true) => changes.push(Change::Added(key.to_string())),
+      (true, false) => changes.push(Change::Removed(key.to_string())),
+      _ if before[key] != after[key] => changes.push(Change::Modified(key.to_string())),
+      _ => (),
+    }
+  }
+  changes
+}
+
+#[cfg(test)]
+mod test_export {
+  use super::*;
+  use json::object;
+
+  #[test]
+  fn changes() {
+    let before = object! {
+      "id" => 0,
+      "type" => "Feature",
+      "properties" => object!{ "wof:id" => 0, "wof:name" => "Null Island", "wof:lastmodified" => 0 },
+      "geometry" => object!{ "coordinates" => vec![0, 0], "type" => "Point" },
+      "bbox" => vec![0, 0, 0, 0],
+    };
+    let after = export_json_value(&before).unwrap();
+    assert_eq!(export_changes(&after, &after), vec![]);
+    let changes = export_changes(&before, &after);
+    assert!(changes.contains(&Change::Added("wof:geomhash".to_string())));
+    assert!(!changes.contains(&Change::Modified("wof:name".to_string())));
+    let mut modified = after.clone();
+    modified["properties"]["wof:name"] = "Null".into();
+    modified["properties"].remove("wof:tags");
+    modified["properties"]["wof:lastmodified"] = 1.into();
+    modified["bbox"] = json::array![0, 0, 1, 1];
+    assert_eq!(
+      export_changes(&after, &modified),
+      vec![
+        Change::Modified("bbox".to_string()),
+        Change::Modified("wof:name".to_string()),
+        Change::Removed("wof:tags".to_string()),
+      ]
+    );
+  }
+
+  fn document() -> JsonValue {
+    object! {
+      "id" => 101748927,
+      "type" => "Feature",
+      "properties" => object!{
+        "geom:area" => 0.5,
+        "geom:bbox" => "0,0,0,0",
+        "wof:geomhash" => "stale",
+        "wof:id" => 101748927,
+        "wof:lastmodified" => 0,
+        "wof:hierarchy" => vec![
+          object!{ "country_id" => 85633147, "region_id" => -1, "localadmin_id" => 101748927 },
+          object!{ "country_id" => 85633147, "region_id" => 85683431 },
+        ],
+        "edtf:cessation" => "2019-01-01",
+        "mz:is_current" => 1,
+      },
+      "geometry" => object!{ "coordinates" => vec![8.5, 41.9], "type" => "Point" },
+      "bbox" => vec![8.5, 41.9, 8.5, 41.9],
+    }
+  }
+
+  #[test]
+  fn options() {
+    let json = document();
+    let exported = export_json_value(&json).unwrap();
+    let properties = &exported["properties"];
+    assert_eq!(properties["wof:geomhash"], "stale");
+    assert_eq!(properties["geom:bbox"], "0,0,0,0");
+    assert_eq!(properties["geom:latitude"], 41.9);
+    assert_eq!(properties["wof:belongsto"], json::array![]);
+    assert_eq!(properties["mz:is_current"], 1);
+    assert_eq!(properties["wof:lastmodified"], 0);
+
+    let exported = export_json_value_with_options(&json, &ExportOptions::default()).unwrap();
+    let properties = &exported["properties"];
+    let geometry = exported["geometry"].as_object().unwrap();
+    assert_eq!(properties["wof:geomhash"], geometry.compute_md5());
+    assert_eq!(properties["geom:bbox"], "8.5,41.9,8.5,41.9");
+    assert_eq!(properties["geom:area"], 0.0);
+    assert_eq!(
+      properties["wof:belongsto"],
+      json::array![85633147, 85683431]
+    );
+    assert_eq!(properties["mz:is_current"], 0);
+    assert!(properties["wof:lastmodified"].as_u64().unwrap() > 0);
+    assert_eq!(
+      export_json_value_with_options(&exported, &ExportOptions::default()).unwrap()["properties"]
+        ["geom:bbox"],
+      "8.5,41.9,8.5,41.9"
+    );
+
+    let exported = export_json_value_with_options(
+      &json,
+      &ExportOptions {
+        defaults: false,
+        geometry: false,
+        belongsto: false,
+        is_current: false,
+        lastmodified: false,
+      },
+    )
+    .unwrap();
+    assert_eq!(exported["properties"], json["properties"]);
+  }
+}
@@ -1,65 +1,276 @@
-use super::tokenizer::{tokenize, Token};
-use super::Predicate;
+use super::functions::check_function;
+use super::path::{is_path, parse_path};
+use super::tokenizer::{syntax_error, tokenize, Lexeme, Token};
+use super::variables::{check_wof_variable, is_wof_variable};
+#[cfg(test)]
+use super::PathItem;
+use super::{Pattern, Predicate};
 use std::convert::TryInto;
 
+/// Parse an expression into a [`Predicate`], `OR` has the lowest precedence, then `AND`, `NOT` and
+/// the comparison operators. Parenthesis can be used to group expressions.
 pub fn parse(expression: String) -> Result<Predicate, String> {
-  let tokens = tokenize(expression);
-  let (predicate, _) = _parse_op(&tokens, 0)?;
+  let mut parser = Parser {
+    expression: &expression,
+    lexemes: tokenize(&expression)?,
+    index: 0,
+  };
+  let predicate = parser.parse_expression(0)?;
+  if let Some(lexeme) = parser.peek() {
+    return Err(parser.error_at(lexeme, "Expected an operator"));
+  }
   Ok(predicate)
 }
 
-fn _parse_op(tokens: &Vec<Token>, index: usize) -> Result<(Predicate, usize), String> {
-  let (mut predicate, mut index) = _parse(&tokens, index)?;
-  while index < tokens.len() {
-    match tokens[index] {
-      Token::And => {
-        let (right, i) = _parse(&tokens, index + 1)?;
-        predicate = Predicate::And(Box::new(predicate), Box::new(right));
-        index = i;
+/// Parse a comma separated list of expressions such as `wof:id, lower(wof:name)`, each expression
+/// is returned with its source text.
+pub fn parse_projection(expression: String) -> Result<Vec<(String, Predicate)>, String> {
+  let chars: Vec<char> = expression.chars().collect();
+  let mut parser = Parser {
+    expression: &expression,
+    lexemes: tokenize(&expression)?,
+    index: 0,
+  };
+  let mut projection: Vec<(String, Predicate)> = vec![];
+  loop {
+    let start = parser
+      .peek()
+      .map_or(chars.len() + 1, |lexeme| lexeme.column);
+    let predicate = parser.parse_expression(0)?;
+    let end = parser
+      .peek()
+      .map_or(chars.len() + 1, |lexeme| lexeme.column);
+    let text: String = chars[start - 1..end - 1].iter().collect();
+    projection.push((text.trim().to_string(), predicate));
+    match parser.peek() {
+      None => return Ok(projection),
+      Some(lexeme) if lexeme.token == Token::Comma => {
+        parser.next()?;
+      }
+      Some(lexeme) => return Err(parser.error_at(lexeme, "Expected a comma")),
+    }
+  }
+}
+
+struct Parser<'a> {
+  expression: &'a str,
+  lexemes: Vec<Lexeme>,
+  index: usize,
+}
+
+/// Precedence of binary logical operators, higher binds tighter.
+fn binary_precedence(token: &Token) -> Option<u8> {
+  match token {
+    Token::Or => Some(1),
+    Token::And => Some(2),
+    _ => None,
+  }
+}
+
+impl<'a> Parser<'a> {
+  fn peek(&self) -> Option<&Lexeme> {
+    self.lexemes.get(self.index)
+  }
+
+  fn peek_token(&self) -> Option<&Token> {
+    self.peek().map(|lexeme| &lexeme.token)
+  }
+
+  fn next(&mut self) -> Result<Lexeme, String> {
+    let lexeme = self.lexemes.get(self.index).cloned().ok_or_else(|| {
+      syntax_error(
+        self.expression,
+        self.expression.chars().count() + 1,
+        "Unexpected end of expression",
+      )
+    })?;
+    self.index += 1;
+    Ok(lexeme)
+  }
+
+  fn error_at(&self, lexeme: &Lexeme, message: &str) -> String {
+    syntax_error(
+      self.expression,
+      lexeme.column,
+      &format!("{}, found `{}`", message, lexeme.text),
+    )
+  }
+
+  fn expect(&mut self, token: Token, message: &str) -> Result<Lexeme, String> {
+    let lexeme = self.next()?;
+    if lexeme.token != token {
+      return Err(self.error_at(&lexeme, message));
+    }
+    Ok(lexeme)
+  }
+
+  /// Precedence climbing over `AND` and `OR`, both are left associative.
+  fn parse_expression(&mut self, min_precedence: u8) -> Result<Predicate, String> {
+    let mut left = self.parse_unary()?;
+    while let Some(precedence) = self.peek_token().and_then(binary_precedence) {
+      if precedence < min_precedence {
+        break;
+      }
+      let operator = self.next()?;
+      let right = self.parse_expression(precedence + 1)?;
+      left = match operator.token {
+        Token::And => Predicate::And(Box::new(left), Box::new(right)),
+        _ => Predicate::Or(Box::new(left), Box::new(right)),
+      };
+    }
+    Ok(left)
+  }
+
+  fn parse_unary(&mut self) -> Result<Predicate, String> {
+    if self.peek_token() == Some(&Token::Not) {
+      self.next()?;
+      return Ok(Predicate::Not(Box::new(self.parse_unary()?)));
+    }
+    self.parse_comparison()
+  }
+
+  fn parse_comparison(&mut self) -> Result<Predicate, String> {
+    let left = self.parse_operand()?;
+    let negated = self.peek_token() == Some(&Token::Not)
+      && matches!(
+        self.lexemes.get(self.index + 1).map(|lexeme| &lexeme.token),
+        Some(Token::In)
+          | Some(Token::Like)
+          | Some(Token::ILike)
+          | Some(Token::Contains)
+          | Some(Token::Any)
+      );
+    if negated {
+      self.next()?;
+    }
+    let operator = match self.peek_token() {
+      Some(Token::Eq)
+      | Some(Token::Neq)
+      | Some(Token::Lt)
+      | Some(Token::Lte)
+      | Some(Token::Gt)
+      | Some(Token::Gte)
+      | Some(Token::In)
+      | Some(Token::Like)
+      | Some(Token::ILike)
+      | Some(Token::Match)
+      | Some(Token::NotMatch)
+      | Some(Token::Contains)
+      | Some(Token::Any) => self.next()?.token,
+      _ => return Ok(left),
+    };
+    let left = Box::new(left);
+    let predicate = match operator {
+      Token::In => Predicate::In(left, self.parse_list()?),
+      Token::Any => Predicate::Any(left, self.parse_list()?),
+      Token::Contains => Predicate::Contains(left, Box::new(self.parse_operand()?)),
+      Token::Like => Predicate::Like(left, Pattern::like(&self.parse_pattern()?, false)?),
+      Token::ILike => Predicate::ILike(left, Pattern::like(&self.parse_pattern()?, true)?),
+      Token::Match => Predicate::Match(left, Pattern::regex(&self.parse_pattern()?)?),
+      Token::NotMatch => Predicate::Not(Box::new(Predicate::Match(
+        left,
+        Pattern::regex(&self.parse_pattern()?)?,
+      ))),
+      Token::Eq => Predicate::Eq(left, Box::new(self.parse_operand()?)),
+      Token::Neq => Predicate::Neq(left, Box::new(self.parse_operand()?)),
+      Token::Lt => Predicate::Lt(left, Box::new(self.parse_operand()?)),
+      Token::Lte => Predicate::Lte(left, Box::new(self.parse_operand()?)),
+      Token::Gt => Predicate::Gt(left, Box::new(self.parse_operand()?)),
+      _ => Predicate::Gte(left, Box::new(self.parse_operand()?)),
+    };
+    if negated {
+      Ok(Predicate::Not(Box::new(predicate)))
+    } else {
+      Ok(predicate)
+    }
+  }
+
+  /// Patterns must be string literals, they are compiled once during the parsing.
+  fn parse_pattern(&mut self) -> Result<String, String> {
+    let lexeme = self.next()?;
+    match lexeme.token {
+      Token::String(pattern) => Ok(pattern),
+      _ => Err(self.error_at(&lexeme, "Expected a string pattern")),
+    }
+  }
+
+  fn parse_operand(&mut self) -> Result<Predicate, String> {
+    let lexeme = self.next()?;
+    match lexeme.token {
+      Token::LeftParenthesis => {
+        let predicate = self.parse_expression(0)?;
+        self.expect(Token::RightParenthesis, "Expected a closing parenthesis")?;
+        Ok(predicate)
       }
-      Token::Or => {
-        let (right, i) = _parse_op(tokens, index + 1)?;
-        predicate = Predicate::Or(Box::new(predicate), Box::new(right));
-        index = i;
+      Token::Variable(ref name) if self.peek_token() == Some(&Token::LeftParenthesis) => {
+        let name = name.to_lowercase();
+        let arguments = self.parse_arguments()?;
+        check_function(&name, arguments.len())
+          .map_err(|e| syntax_error(self.expression, lexeme.column, &e))?;
+        Ok(Predicate::Function(name, arguments))
       }
-      _ => {
-        return Err(format!(
-          "Token {} must be an operator, found {:?}",
-          index, tokens[index]
-        ))
+      Token::Variable(ref variable) if is_wof_variable(variable) => {
+        check_wof_variable(variable)
+          .map_err(|e| syntax_error(self.expression, lexeme.column, &e))?;
+        lexeme.token.try_into()
+      }
+      Token::Variable(ref variable) if is_path(variable) => {
+        Ok(Predicate::Path(parse_path(variable).map_err(|e| {
+          syntax_error(self.expression, lexeme.column, &e)
+        })?))
       }
+      Token::Variable(_)
+      | Token::Number(_)
+      | Token::String(_)
+      | Token::Boolean(_)
+      | Token::Null => lexeme.token.try_into(),
+      _ => Err(self.error_at(&lexeme, "Expected a value")),
     }
   }
-  Ok((predicate, index))
-}
 
-fn _parse(tokens: &Vec<Token>, mut index: usize) -> Result<(Predicate, usize), String> {
-  match tokens[index] {
-    Token::Variable(_) | Token::Number(_) | Token::String(_) | Token::Boolean(_) | Token::Null => {
-      let left: Predicate = tokens[index].clone().try_into()?;
-      index = index + 1;
-      match tokens[index] {
-        Token::Eq => {
-          index = index + 1;
-          return Ok((
-            Predicate::Eq(Box::new(left), Box::new(tokens[index].clone().try_into()?)),
-            index + 1,
-          ));
-        }
-        Token::Neq => {
-          index = index + 1;
-          return Ok((
-            Predicate::Neq(Box::new(left), Box::new(tokens[index].clone().try_into()?)),
-            index + 1,
-          ));
-        }
-        _ => (),
+  /// Parse the arguments of a function call such as `coalesce(wof:population, gn:population)`.
+  fn parse_arguments(&mut self) -> Result<Vec<Predicate>, String> {
+    self.expect(Token::LeftParenthesis, "Expected an opening parenthesis")?;
+    let mut arguments: Vec<Predicate> = vec![];
+    if self.peek_token() == Some(&Token::RightParenthesis) {
+      self.next()?;
+      return Ok(arguments);
+    }
+    loop {
+      arguments.push(self.parse_expression(0)?);
+      let lexeme = self.next()?;
+      match lexeme.token {
+        Token::Comma => continue,
+        Token::RightParenthesis => return Ok(arguments),
+        _ => return Err(self.error_at(&lexeme, "Expected a comma or a closing parenthesis")),
       }
     }
-    _ => (),
-  };
+  }
 
-  Err(format!("Incorrect token found {:?}", tokens[index]))
+  /// Parse a list of literals such as `('locality', 'localadmin')`.
+  fn parse_list(&mut self) -> Result<Vec<Predicate>, String> {
+    self.expect(Token::LeftParenthesis, "Expected an opening parenthesis")?;
+    let mut list: Vec<Predicate> = vec![];
+    if self.peek_token() == Some(&Token::RightParenthesis) {
+      self.next()?;
+      return Ok(list);
+    }
+    loop {
+      let lexeme = self.next()?;
+      match lexeme.token {
+        Token::Number(_) | Token::String(_) | Token::Boolean(_) | Token::Null => {
+          list.push(lexeme.token.try_into()?)
+        }
+        _ => return Err(self.error_at(&lexeme, "Expected a literal")),
+      }
+      let lexeme = self.next()?;
+      match lexeme.token {
+        Token::Comma => continue,
+        Token::RightParenthesis => return Ok(list),
+        _ => return Err(self.error_at(&lexeme, "Expected a comma or a closing parenthesis")),
+      }
+    }
+  }
 }
 
 #[cfg(test)]
@@ -143,4 +354,318 @@ mod test_deserializer {
 
     Ok(())
   }
+
+  #[test]
+  fn parse_ordering_expression() -> Result<(), String> {
+    assert_eq!(
+      parse("mz:min_zoom <= 8".to_string())?,
+      Predicate::Lte(
+        Box::new(Predicate::Variable("mz:min_zoom".to_string())),
+        Box::new(Predicate::Number(8.0))
+      )
+    );
+    assert_eq!(
+      parse("wof:population > 100000 AND lastmodified >= 1600000000".to_string())?,
+      Predicate::And(
+        Box::new(Predicate::Gt(
+          Box::new(Predicate::Variable("wof:population".to_string())),
+          Box::new(Predicate::Number(100000.0))
+        )),
+        Box::new(Predicate::Gte(
+          Box::new(Predicate::Variable("lastmodified".to_string())),
+          Box::new(Predicate::Number(1600000000.0))
+        ))
+      )
+    );
+    assert_eq!(
+      parse("wof:name < 'B'".to_string())?,
+      Predicate::Lt(
+        Box::new(Predicate::Variable("wof:name".to_string())),
+        Box::new(Predicate::String("B".to_string()))
+      )
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn parse_in_expression() -> Result<(), String> {
+    assert_eq!(
+      parse("wof:placetype in ('locality', 'localadmin')".to_string())?,
+      Predicate::In(
+        Box::new(Predicate::Variable("wof:placetype".to_string())),
+        vec![
+          Predicate::String("locality".to_string()),
+          Predicate::String("localadmin".to_string())
+        ]
+      )
+    );
+    assert_eq!(
+      parse("wof:id NOT IN (1,2) AND v = 3".to_string())?,
+      Predicate::And(
+        Box::new(Predicate::Not(Box::new(Predicate::In(
+          Box::new(Predicate::Variable("wof:id".to_string())),
+          vec![Predicate::Number(1.0), Predicate::Number(2.0)]
+        )))),
+        Box::new(Predicate::Eq(
+          Box::new(Predicate::Variable("v".to_string())),
+          Box::new(Predicate::Number(3.0))
+        ))
+      )
+    );
+    assert_eq!(
+      parse("v in ()".to_string())?,
+      Predicate::In(Box::new(Predicate::Variable("v".to_string())), vec![])
+    );
+    assert!(parse("v in 'a'".to_string()).is_err());
+    assert!(parse("v in ('a'".to_string()).is_err());
+    assert!(parse("v in ('a' 'b')".to_string()).is_err());
+    Ok(())
+  }
+
+  #[test]
+  fn parse_grouped_expression() -> Result<(), String> {
+    let eq = |variable: &str, value: f64| {
+      Predicate::Eq(
+        Box::new(Predicate::Variable(variable.to_string())),
+        Box::new(Predicate::Number(value)),
+      )
+    };
+    assert_eq!(
+      parse("(a = 1 or b = 2) and not c = 3".to_string())?,
+      Predicate::And(
+        Box::new(Predicate::Or(
+          Box::new(eq("a", 1.0)),
+          Box::new(eq("b", 2.0))
+        )),
+        Box::new(Predicate::Not(Box::new(eq("c", 3.0))))
+      )
+    );
+    assert_eq!(
+      parse("a=1 OR b=2 OR c=3".to_string())?,
+      Predicate::Or(
+        Box::new(Predicate::Or(
+          Box::new(eq("a", 1.0)),
+          Box::new(eq("b", 2.0))
+        )),
+        Box::new(eq("c", 3.0))
+      )
+    );
+    assert_eq!(
+      parse("a = 1 and (b = 2 or (c = 3))".to_string())?,
+      Predicate::And(
+        Box::new(eq("a", 1.0)),
+        Box::new(Predicate::Or(
+          Box::new(eq("b", 2.0)),
+          Box::new(eq("c", 3.0))
+        ))
+      )
+    );
+    assert_eq!(
+      parse("\tnot\ttrue ".to_string())?,
+      Predicate::Not(Box::new(Predicate::Boolean(true)))
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn parse_errors() {
+    assert_eq!(
+      parse("(a = 1 or b = 2".to_string()).unwrap_err(),
+      "Unexpected end of expression at column 16\n(a = 1 or b = 2\n               ^"
+    );
+    assert_eq!(
+      parse("a = 1 b = 2".to_string()).unwrap_err(),
+      "Expected an operator, found `b` at column 7\na = 1 b = 2\n      ^"
+    );
+    assert_eq!(
+      parse("a = and".to_string()).unwrap_err(),
+      "Expected a value, found `and` at column 5\na = and\n    ^"
+    );
+    assert_eq!(
+      parse("a in (1, b)".to_string()).unwrap_err(),
+      "Expected a literal, found `b` at column 10\na in (1, b)\n         ^"
+    );
+  }
+
+  #[test]
+  fn parse_pattern_expression() -> Result<(), String> {
+    let name = || Box::new(Predicate::Variable("wof:name".to_string()));
+    assert_eq!(
+      parse("wof:name like 'Saint-%'".to_string())?,
+      Predicate::Like(name(), Pattern::like("Saint-%", false)?)
+    );
+    assert_eq!(
+      parse("wof:name NOT ILIKE 'saint-%'".to_string())?,
+      Predicate::Not(Box::new(Predicate::ILike(
+        name(),
+        Pattern::like("saint-%", true)?
+      )))
+    );
+    assert_eq!(
+      parse("wof:name ~ '^San\\s'".to_string())?,
+      Predicate::Match(name(), Pattern::regex("^San\\s")?)
+    );
+    assert_eq!(
+      parse("wof:name !~ 'San'".to_string())?,
+      Predicate::Not(Box::new(Predicate::Match(name(), Pattern::regex("San")?)))
+    );
+    assert_eq!(
+      parse("wof:name like wof:label".to_string()).unwrap_err(),
+      "Expected a string pattern, found `wof:label` at column 15\nwof:name like wof:label\n              ^"
+    );
+    assert!(parse("wof:name ~ '('".to_string()).is_err());
+    Ok(())
+  }
+
+  #[test]
+  fn parse_path_expression() -> Result<(), String> {
+    assert_eq!(
+      parse("wof:hierarchy[0].country_id = 85633147".to_string())?,
+      Predicate::Eq(
+        Box::new(Predicate::Path(vec![
+          PathItem::Key("wof:hierarchy".to_string()),
+          PathItem::Index(0),
+          PathItem::Key("country_id".to_string())
+        ])),
+        Box::new(Predicate::Number(85633147.0))
+      )
+    );
+    assert_eq!(
+      parse("wof:belongsto not contains 85633147".to_string())?,
+      Predicate::Not(Box::new(Predicate::Contains(
+        Box::new(Predicate::Variable("wof:belongsto".to_string())),
+        Box::new(Predicate::Number(85633147.0))
+      )))
+    );
+    assert_eq!(
+      parse("wof:lang any ('fre', 'eng')".to_string())?,
+      Predicate::Any(
+        Box::new(Predicate::Variable("wof:lang".to_string())),
+        vec![
+          Predicate::String("fre".to_string()),
+          Predicate::String("eng".to_string())
+        ]
+      )
+    );
+    assert_eq!(
+      parse("wof:concordances..gn:id = 1".to_string()).unwrap_err(),
+      "Empty key in path `wof:concordances..gn:id` at column 1\nwof:concordances..gn:id = 1\n^"
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn parse_function_expression() -> Result<(), String> {
+    assert_eq!(
+      parse("LEN(wof:lang) > 1 and exists(wof:concordances.gn:id)".to_string())?,
+      Predicate::And(
+        Box::new(Predicate::Gt(
+          Box::new(Predicate::Function(
+            "len".to_string(),
+            vec![Predicate::Variable("wof:lang".to_string())]
+          )),
+          Box::new(Predicate::Number(1.0))
+        )),
+        Box::new(Predicate::Function(
+          "exists".to_string(),
+          vec![Predicate::Path(vec![
+            PathItem::Key("wof:concordances".to_string()),
+            PathItem::Key("gn:id".to_string())
+          ])]
+        ))
+      )
+    );
+    assert_eq!(
+      parse("coalesce(wof:population, gn:population, 0) >= 1000".to_string())?,
+      Predicate::Gte(
+        Box::new(Predicate::Function(
+          "coalesce".to_string(),
+          vec![
+            Predicate::Variable("wof:population".to_string()),
+            Predicate::Variable("gn:population".to_string()),
+            Predicate::Number(0.0)
+          ]
+        )),
+        Box::new(Predicate::Number(1000.0))
+      )
+    );
+    assert_eq!(
+      parse("lower(wof:name) = 'x'".to_string()),
+      Ok(Predicate::Eq(
+        Box::new(Predicate::Function(
+          "lower".to_string(),
+          vec![Predicate::Variable("wof:name".to_string())]
+        )),
+        Box::new(Predicate::String("x".to_string()))
+      ))
+    );
+    assert_eq!(
+      parse("upper(wof:name) = 'X'".to_string()).unwrap_err(),
+      "Unknown function `upper` at column 1\nupper(wof:name) = 'X'\n^"
+    );
+    assert_eq!(
+      parse("wof:id = len()".to_string()).unwrap_err(),
+      "Function `len` expects 1 argument(s), got 0 at column 10\nwof:id = len()\n         ^"
+    );
+    assert!(parse("len(wof:lang = 1".to_string()).is_err());
+    Ok(())
+  }
+
+  #[test]
+  fn parse_wof_variable_expression() -> Result<(), String> {
+    assert_eq!(
+      parse("$is_current and $placetype = 'locality'".to_string())?,
+      Predicate::And(
+        Box::new(Predicate::Variable("$is_current".to_string())),
+        Box::new(Predicate::Eq(
+          Box::new(Predicate::Variable("$placetype".to_string())),
+          Box::new(Predicate::String("locality".to_string()))
+        ))
+      )
+    );
+    assert_eq!(
+      parse("not $is_curent".to_string()).unwrap_err(),
+      "Unknown variable `$is_curent` at column 5\nnot $is_curent\n    ^"
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn parse_projection_expression() -> Result<(), String> {
+    assert_eq!(
+      parse_projection(
+        "wof:id,coalesce(wof:population, 0) , wof:hierarchy[0].country_id".to_string()
+      )?,
+      vec![
+        (
+          "wof:id".to_string(),
+          Predicate::Variable("wof:id".to_string())
+        ),
+        (
+          "coalesce(wof:population, 0)".to_string(),
+          Predicate::Function(
+            "coalesce".to_string(),
+            vec![
+              Predicate::Variable("wof:population".to_string()),
+              Predicate::Number(0.0)
+            ]
+          )
+        ),
+        (
+          "wof:hierarchy[0].country_id".to_string(),
+          Predicate::Path(vec![
+            PathItem::Key("wof:hierarchy".to_string()),
+            PathItem::Index(0),
+            PathItem::Key("country_id".to_string())
+          ])
+        ),
+      ]
+    );
+    assert_eq!(
+      parse_projection("wof:id wof:name".to_string()).unwrap_err(),
+      "Expected a comma, found `wof:name` at column 8\nwof:id wof:name\n       ^"
+    );
+    assert!(parse_projection("wof:id,".to_string()).is_err());
+    Ok(())
+  }
 }
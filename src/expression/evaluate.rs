@@ -1,7 +1,11 @@
-use super::Predicate;
+use super::functions::call;
+use super::path::resolve_path;
+use super::variables::{get_wof_variable, is_wof_variable};
+use super::{PathItem, Predicate};
 use crate::utils::JsonUtils;
 use crate::wof::WOFGeoJSON;
-use crate::JsonValue;
+use crate::{JsonObject, JsonValue};
+use std::cmp::Ordering;
 
 pub trait Evaluate {
   fn eval(&self, predicate: &Predicate) -> Result<Predicate, String> {
@@ -13,9 +17,57 @@ pub trait Evaluate {
         self.eval(&left)?.as_bool()? || self.eval(&right)?.as_bool()?,
       )),
       Predicate::Eq(left, right) => Ok(Predicate::Boolean(self.eval(&left)? == self.eval(&right)?)),
+      Predicate::Neq(left, right) => Ok(Predicate::Boolean(self.eval(left)? != self.eval(right)?)),
+      Predicate::In(left, list) => {
+        let value = self.eval(left)?;
+        for element in list {
+          if self.eval(element)? == value {
+            return Ok(Predicate::Boolean(true));
+          }
+        }
+        Ok(Predicate::Boolean(false))
+      }
+      Predicate::Lt(left, right) => Ok(Predicate::Boolean(
+        self.eval(left)?.compare(&self.eval(right)?)? == Some(Ordering::Less),
+      )),
+      Predicate::Lte(left, right) => Ok(Predicate::Boolean(matches!(
+        self.eval(left)?.compare(&self.eval(right)?)?,
+        Some(Ordering::Less) | Some(Ordering::Equal)
+      ))),
+      Predicate::Gt(left, right) => Ok(Predicate::Boolean(
+        self.eval(left)?.compare(&self.eval(right)?)? == Some(Ordering::Greater),
+      )),
+      Predicate::Gte(left, right) => Ok(Predicate::Boolean(matches!(
+        self.eval(left)?.compare(&self.eval(right)?)?,
+        Some(Ordering::Greater) | Some(Ordering::Equal)
+      ))),
+      Predicate::Like(left, pattern)
+      | Predicate::ILike(left, pattern)
+      | Predicate::Match(left, pattern) => {
+        Ok(Predicate::Boolean(self.eval(left)?.is_match(pattern)?))
+      }
+      Predicate::Contains(left, right) => Ok(Predicate::Boolean(
+        self.eval(left)?.contains(&self.eval(right)?)?,
+      )),
+      Predicate::Any(left, list) => {
+        let list = list
+          .iter()
+          .map(|element| self.eval(element))
+          .collect::<Result<Vec<Predicate>, String>>()?;
+        Ok(Predicate::Boolean(self.eval(left)?.contains_any(&list)))
+      }
       Predicate::Not(predicate) => Ok(Predicate::Boolean(!(self.eval(&predicate)?.as_bool()?))),
       Predicate::Boolean(b) => Ok(Predicate::Boolean(b == &true)),
       Predicate::Variable(s) => self.get_variable_value(s),
+      Predicate::Path(path) => self.get_path(path),
+      Predicate::Function(name, arguments) => call(
+        name,
+        arguments
+          .iter()
+          .map(|argument| self.eval(argument))
+          .collect::<Result<Vec<Predicate>, String>>()?,
+        self.get_geometry(),
+      ),
       _ => Ok(predicate.clone()),
     }
   }
@@ -23,19 +75,16 @@ pub trait Evaluate {
   fn get_variable_value(&self, key: &String) -> Result<Predicate, String> {
     match key.as_str() {
       "geom_type" => self.get_geometry_type(),
+      key if is_wof_variable(key) => self.get_wof_variable(key),
       key => self.get_property(key),
     }
   }
-  fn get_geometry_type(&self) -> Result<Predicate, String>;
-  fn get_property(&self, key: &str) -> Result<Predicate, String>;
-}
-
-impl Evaluate for WOFGeoJSON<'_> {
+  fn get_wof_variable(&self, key: &str) -> Result<Predicate, String>;
   fn get_geometry_type(&self) -> Result<Predicate, String> {
     self
-      .geometry
+      .get_geometry()?
       .get("type")
-      .ok_or(format!("Evaluated json must contains geometry.type"))
+      .ok_or("Evaluated json must contains geometry.type".to_string())
       .map(|value| {
         value
           .as_str()
@@ -43,44 +92,59 @@ impl Evaluate for WOFGeoJSON<'_> {
           .unwrap_or(Predicate::Null)
       })
   }
+  fn get_geometry(&self) -> Result<&JsonObject, String>;
+  fn get_properties(&self) -> Result<&JsonObject, String>;
 
   fn get_property(&self, key: &str) -> Result<Predicate, String> {
     self
-      .properties
+      .get_properties()?
       .get(key)
       .map_or(Ok(Predicate::Null), self::json_value_to_predicate)
   }
+
+  fn get_path(&self, path: &[PathItem]) -> Result<Predicate, String> {
+    resolve_path(self.get_properties()?, path)
+      .map_or(Ok(Predicate::Null), self::json_value_to_predicate)
+  }
+}
+
+impl Evaluate for WOFGeoJSON<'_> {
+  fn get_geometry(&self) -> Result<&JsonObject, String> {
+    Ok(self.geometry)
+  }
+
+  fn get_wof_variable(&self, key: &str) -> Result<Predicate, String> {
+    get_wof_variable(self, key)
+  }
+
+  fn get_properties(&self) -> Result<&JsonObject, String> {
+    Ok(self.properties)
+  }
 }
 
 impl Evaluate for JsonValue {
-  fn get_geometry_type(&self) -> Result<Predicate, String> {
+  fn get_wof_variable(&self, key: &str) -> Result<Predicate, String> {
+    get_wof_variable(&WOFGeoJSON::as_valid_wof_geojson(self)?, key)
+  }
+
+  fn get_geometry(&self) -> Result<&JsonObject, String> {
     self
       .as_object()
       .ok_or(format!("Evaluated json must be an object!"))?
       .get("geometry")
       .ok_or(format!("Evaluated json must contains a geometry object!"))?
       .as_object()
-      .ok_or(format!("Evaluated json geometry must be an object!"))?
-      .get("type")
-      .ok_or(format!("Evaluated json must contains geometry.type"))
-      .map(|value| {
-        value
-          .as_str()
-          .map(|value| Predicate::String(value.to_string()))
-          .unwrap_or(Predicate::Null)
-      })
+      .ok_or(format!("Evaluated json geometry must be an object!"))
   }
 
-  fn get_property(&self, key: &str) -> Result<Predicate, String> {
+  fn get_properties(&self) -> Result<&JsonObject, String> {
     self
       .as_object()
       .ok_or(format!("Evaluated json must be an object!"))?
       .get("properties")
       .ok_or(format!("Evaluated json must contains a properties object!"))?
       .as_object()
-      .ok_or(format!("Evaluated json properties must be an object!"))?
-      .get(key)
-      .map_or(Ok(Predicate::Null), self::json_value_to_predicate)
+      .ok_or(format!("Evaluated json properties must be an object!"))
   }
 }
 
@@ -90,7 +154,19 @@ fn json_value_to_predicate(value: &JsonValue) -> Result<Predicate, String> {
     JsonValue::String(s) => Ok(Predicate::String(s.to_string())),
     JsonValue::Number(_) => Ok(Predicate::Number(value.as_f64().unwrap())),
     JsonValue::Boolean(b) => Ok(Predicate::Boolean(*b)),
-    _ => Ok(Predicate::Null),
+    JsonValue::Array(array) => Ok(Predicate::Array(
+      array
+        .iter()
+        .map(json_value_to_predicate)
+        .collect::<Result<Vec<Predicate>, String>>()?,
+    )),
+    JsonValue::Object(object) => Ok(Predicate::Object(
+      object
+        .iter()
+        .map(|(key, value)| Ok((key.to_string(), json_value_to_predicate(value)?)))
+        .collect::<Result<Vec<(String, Predicate)>, String>>()?,
+    )),
+    JsonValue::Null => Ok(Predicate::Null),
   }
 }
 
@@ -181,6 +257,389 @@ mod test_expression {
       Predicate::Boolean(true)
     );
 
+    for (expression, expected) in vec![
+      ("wof:placetype != 'county'", true),
+      ("wof:placetype <> 'localadmin'", false),
+      ("geom:src != 'osm'", true),
+      ("wof:placetype in ('locality', 'localadmin')", true),
+      ("wof:placetype in ('locality', 'county')", false),
+      ("wof:placetype not in ('locality', 'county')", true),
+      ("wof:placetype NOT IN ('localadmin')", false),
+      ("wof:id in (1, 101748927)", true),
+      ("geom:src in ('osm', null)", true),
+      ("wof:placetype in ()", false),
+    ] {
+      assert_eq!(
+        wof_obj.eval(&Predicate::try_from(expression.to_string())?)?,
+        Predicate::Boolean(expected),
+        "{}",
+        expression
+      );
+      assert_eq!(
+        json.eval(&Predicate::try_from(expression.to_string())?)?,
+        Predicate::Boolean(expected),
+        "{}",
+        expression
+      );
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn evaluate_pattern_predicate() -> Result<(), String> {
+    let json = object! {
+      "type" => "Feature",
+      "properties" => object!{
+        "wof:id" => 1125326587,
+        "wof:name" => "Arrondissement de Saint-Benoît",
+        "wof:label" => "San Benito",
+        "wof:population" => 115239,
+      },
+      "geometry" => object!{
+        "coordinates" => vec![55.66667, -21.08333],
+        "type" => "Point"
+      },
+      "bbox" => vec![55.66667, -21.08333, 55.66667, -21.08333],
+      "id" => 1125326587,
+    };
+    let wof_obj = WOFGeoJSON::as_valid_wof_geojson(&json)?;
+
+    for (expression, expected) in vec![
+      ("wof:name like '%Saint-%'", true),
+      ("wof:name like 'Saint-%'", false),
+      ("wof:name like '%saint-%'", false),
+      ("wof:name ilike '%saint-%'", true),
+      ("wof:name not ilike '%SAINT-BENO_T'", false),
+      ("wof:label ~ '^San\\s'", true),
+      ("wof:label !~ '^San\\s'", false),
+      ("wof:name ~ '^San\\s'", false),
+      ("geom:src like '%'", false),
+      ("geom:src ~ '.*'", false),
+    ] {
+      assert_eq!(
+        wof_obj.eval(&Predicate::try_from(expression.to_string())?)?,
+        Predicate::Boolean(expected),
+        "{}",
+        expression
+      );
+      assert_eq!(
+        json.eval(&Predicate::try_from(expression.to_string())?)?,
+        Predicate::Boolean(expected),
+        "{}",
+        expression
+      );
+    }
+
+    assert!(json
+      .eval(&Predicate::try_from(
+        "wof:population like '1%'".to_string()
+      )?)
+      .is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn evaluate_ordering_predicate() -> Result<(), String> {
+    let json = object! {
+      "type" => "Feature",
+      "properties" => object!{
+        "wof:id" => 101748927,
+        "wof:name" => "Ajaccio",
+        "wof:population" => 68587,
+        "mz:min_zoom" => 8.0,
+      },
+      "geometry" => object!{
+        "coordinates" => vec![8.7, 41.9],
+        "type" => "Point"
+      },
+      "bbox" => vec![8.7, 41.9, 8.7, 41.9],
+      "id" => 101748927,
+    };
+    let wof_obj = WOFGeoJSON::as_valid_wof_geojson(&json)?;
+
+    for (expression, expected) in vec![
+      ("mz:min_zoom <= 8", true),
+      ("mz:min_zoom < 8", false),
+      ("mz:min_zoom >= 8", true),
+      ("mz:min_zoom > 7.5", true),
+      ("wof:population > 100000", false),
+      ("wof:population < 100000", true),
+      ("wof:name >= 'Ajaccio'", true),
+      ("wof:name < 'Bastia'", true),
+      ("wof:name > 'Bastia'", false),
+      ("geom:src > 0", false),
+      ("geom:src <= 'osm'", false),
+    ] {
+      assert_eq!(
+        wof_obj.eval(&Predicate::try_from(expression.to_string())?)?,
+        Predicate::Boolean(expected),
+        "{}",
+        expression
+      );
+      assert_eq!(
+        json.eval(&Predicate::try_from(expression.to_string())?)?,
+        Predicate::Boolean(expected),
+        "{}",
+        expression
+      );
+    }
+
+    assert!(wof_obj
+      .eval(&Predicate::try_from("wof:name > 10".to_string())?)
+      .is_err());
+    assert!(json
+      .eval(&Predicate::try_from("wof:population <= 'big'".to_string())?)
+      .is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn evaluate_path_predicate() -> Result<(), String> {
+    let json = object! {
+      "type" => "Feature",
+      "properties" => object!{
+        "wof:id" => 101748927,
+        "wof:name" => "Ajaccio",
+        "wof:lang" => vec!["fre", "cos"],
+        "wof:belongsto" => vec![102191581, 85633147, 404227445],
+        "wof:hierarchy" => vec![object!{ "country_id" => 85633147, "region_id" => 404227445 }],
+        "wof:concordances" => object!{ "gn:id" => 2594627 },
+      },
+      "geometry" => object!{
+        "coordinates" => vec![8.7, 41.9],
+        "type" => "Point"
+      },
+      "bbox" => vec![8.7, 41.9, 8.7, 41.9],
+      "id" => 101748927,
+    };
+    let wof_obj = WOFGeoJSON::as_valid_wof_geojson(&json)?;
+
+    for (expression, expected) in [
+      ("wof:hierarchy[0].country_id = 85633147", true),
+      ("wof:hierarchy[0].region_id != 404227445", false),
+      ("wof:hierarchy[1].country_id = null", true),
+      ("wof:concordances.gn:id = 2594627", true),
+      ("wof:concordances.wd:id = null", true),
+      ("wof:lang[1] = 'cos'", true),
+      ("wof:belongsto contains 85633147", true),
+      ("wof:belongsto not contains 1", true),
+      ("wof:lang contains 'eng'", false),
+      ("wof:name contains 'acc'", true),
+      ("geom:src contains 'osm'", false),
+      ("wof:lang any ('eng', 'fre')", true),
+      ("wof:lang not any ('eng', 'ita')", true),
+      ("wof:name any ('Ajaccio')", true),
+      ("geom:src any ('osm')", false),
+    ] {
+      assert_eq!(
+        wof_obj.eval(&Predicate::try_from(expression.to_string())?)?,
+        Predicate::Boolean(expected),
+        "{}",
+        expression
+      );
+      assert_eq!(
+        json.eval(&Predicate::try_from(expression.to_string())?)?,
+        Predicate::Boolean(expected),
+        "{}",
+        expression
+      );
+    }
+
+    assert!(json
+      .eval(&Predicate::try_from("wof:id contains 1".to_string())?)
+      .is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn evaluate_function_predicate() -> Result<(), String> {
+    let json = object! {
+      "type" => "Feature",
+      "properties" => object!{
+        "wof:id" => 101748927,
+        "wof:name" => "Ajaccio",
+        "wof:lang" => vec!["fre", "cos"],
+        "gn:population" => 68587,
+        "geom:latitude" => 41.926,
+        "geom:longitude" => -8.737,
+        "wof:concordances" => object!{ "gn:id" => 2594627 },
+      },
+      "geometry" => object!{
+        "coordinates" => vec![8.7, 41.9],
+        "type" => "Point"
+      },
+      "bbox" => vec![8.7, 41.9, 8.7, 41.9],
+      "id" => 101748927,
+    };
+    let wof_obj = WOFGeoJSON::as_valid_wof_geojson(&json)?;
+
+    for (expression, expected) in [
+      ("len(wof:lang) = 2", true),
+      ("len(wof:name) = 7", true),
+      ("len(wof:concordances) = 1", true),
+      ("len(geom:src) = null", true),
+      ("lower(wof:name) = 'ajaccio'", true),
+      ("lower(geom:src) = null", true),
+      ("exists(wof:concordances)", true),
+      ("exists(wof:concordances.wd:id)", false),
+      ("not exists(geom:src)", true),
+      ("coalesce(wof:population, gn:population) = 68587", true),
+      ("coalesce(wof:population, 0) = 0", true),
+      ("starts_with(wof:name, 'Aja')", true),
+      ("starts_with(lower(wof:name), 'Aja')", false),
+      ("starts_with(geom:src, 'osm')", false),
+      ("abs(geom:longitude) > 8", true),
+      ("round(geom:latitude) = 42", true),
+      ("round(geom:latitude, 1) = 41.9", true),
+    ] {
+      assert_eq!(
+        wof_obj.eval(&Predicate::try_from(expression.to_string())?)?,
+        Predicate::Boolean(expected),
+        "{}",
+        expression
+      );
+      assert_eq!(
+        json.eval(&Predicate::try_from(expression.to_string())?)?,
+        Predicate::Boolean(expected),
+        "{}",
+        expression
+      );
+    }
+
+    assert!(json
+      .eval(&Predicate::try_from("abs(wof:name) > 1".to_string())?)
+      .is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn evaluate_spatial_predicate() -> Result<(), String> {
+    let json = object! {
+      "type" => "Feature",
+      "properties" => object!{
+        "wof:id" => 101748927,
+        "wof:name" => "Ajaccio",
+      },
+      "geometry" => object!{
+        "coordinates" => vec![vec![
+          vec![8.585396,41.873571], vec![8.826011,41.873571], vec![8.826011,41.971536], vec![8.585396,41.968222], vec![8.585396,41.873571]
+        ]],
+        "type" => "Polygon"
+      },
+      "bbox" => vec![8.585396, 41.873571, 8.826011, 41.971536],
+      "id" => 101748927,
+    };
+    let wof_obj = WOFGeoJSON::as_valid_wof_geojson(&json)?;
+
+    for (expression, expected) in [
+      ("intersects_bbox(8.5, 41.5, 9.6, 43.1)", true),
+      ("intersects_bbox(8.8, 41.9, 9.6, 43.1)", true),
+      ("intersects_bbox(9.0, 41.5, 9.6, 43.1)", false),
+      ("within_bbox(8.5, 41.5, 9.6, 43.1)", true),
+      ("within_bbox(8.8, 41.9, 9.6, 43.1)", false),
+      ("contains_point(8.7, 41.9)", true),
+      ("contains_point(8.59, 41.97)", false),
+      ("distance_km(8.7, 41.9) < 5", true),
+      ("distance_km(9.45, 42.7) < 50", false),
+      ("wof:name = 'Ajaccio' and contains_point(8.7, 41.9)", true),
+    ] {
+      assert_eq!(
+        wof_obj.eval(&Predicate::try_from(expression.to_string())?)?,
+        Predicate::Boolean(expected),
+        "{}",
+        expression
+      );
+      assert_eq!(
+        json.eval(&Predicate::try_from(expression.to_string())?)?,
+        Predicate::Boolean(expected),
+        "{}",
+        expression
+      );
+    }
+
+    assert!(json
+      .eval(&Predicate::try_from(
+        "contains_point(wof:name, 41.9)".to_string()
+      )?)
+      .is_err());
+    assert!(object! { "properties" => object!{} }
+      .eval(&Predicate::try_from(
+        "contains_point(8.7, 41.9)".to_string()
+      )?)
+      .is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn evaluate_wof_variable_predicate() -> Result<(), String> {
+    let json = object! {
+      "type" => "Feature",
+      "properties" => object!{
+        "wof:id" => 1125326587,
+        "wof:name" => "Arrondissement de Saint-Benoît",
+        "wof:placetype" => "localadmin",
+        "wof:country" => "RE",
+        "wof:repo" => "whosonfirst-data-admin-re",
+        "wof:parent_id" => 85671199,
+        "wof:lastmodified" => 1566624060,
+        "wof:superseded_by" => vec![] as Vec<i32>,
+        "wof:supersedes" => vec![1125326585],
+        "edtf:deprecated" => "uuuu",
+        "mz:is_current" => 0,
+        "geom:latitude" => -21.08333,
+        "geom:longitude" => 55.66667,
+      },
+      "geometry" => object!{
+        "coordinates" => vec![vec![
+          vec![55.0, -21.0], vec![56.0, -21.0], vec![56.0, -22.0], vec![55.0, -22.0], vec![55.0, -21.0]
+        ]],
+        "type" => "Polygon"
+      },
+      "bbox" => vec![55.0, -22.0, 56.0, -21.0],
+      "id" => 1125326587,
+    };
+    let wof_obj = WOFGeoJSON::as_valid_wof_geojson(&json)?;
+
+    for (expression, expected) in [
+      ("$id = 1125326587", true),
+      ("$is_current", false),
+      ("$is_deprecated", false),
+      ("$is_superseded", false),
+      ("$is_superseding", true),
+      ("$is_ceased", false),
+      ("$is_alt_geom", false),
+      ("$placetype = 'localadmin'", true),
+      ("$name like 'Arrondissement%'", true),
+      (
+        "$country = 'RE' and $repo = 'whosonfirst-data-admin-re'",
+        true,
+      ),
+      ("$lat = -21.08333 and $lon = 55.66667", true),
+      ("$parent_id = 85671199", true),
+      ("$lastmodified > 1500000000", true),
+      ("$area = 1", true),
+      ("$area_square_m > 10000000000", true),
+    ] {
+      assert_eq!(
+        wof_obj.eval(&Predicate::try_from(expression.to_string())?)?,
+        Predicate::Boolean(expected),
+        "{}",
+        expression
+      );
+      assert_eq!(
+        json.eval(&Predicate::try_from(expression.to_string())?)?,
+        Predicate::Boolean(expected),
+        "{}",
+        expression
+      );
+    }
+
     Ok(())
   }
 }
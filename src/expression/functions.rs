@@ -0,0 +1,230 @@
+use super::Predicate;
+use crate::utils::compute::{compute_distance_km, GeoCompute};
+use crate::JsonObject;
+
+/// Name, minimum and maximum number of arguments of the built-in functions.
+const FUNCTIONS: [(&str, usize, usize); 11] = [
+  ("len", 1, 1),
+  ("lower", 1, 1),
+  ("exists", 1, 1),
+  ("coalesce", 1, usize::MAX),
+  ("starts_with", 2, 2),
+  ("abs", 1, 1),
+  ("round", 1, 2),
+  ("intersects_bbox", 4, 4),
+  ("within_bbox", 4, 4),
+  ("contains_point", 2, 2),
+  ("distance_km", 2, 2),
+];
+
+/// Check that the function exists and accepts this number of arguments.
+pub fn check_function(name: &str, arguments: usize) -> Result<(), String> {
+  let (_, min, max) = FUNCTIONS
+    .iter()
+    .find(|(function, _, _)| *function == name)
+    .ok_or_else(|| format!("Unknown function `{}`", name))?;
+  if arguments < *min || arguments > *max {
+    return Err(match (min, max) {
+      (min, max) if min == max => {
+        format!(
+          "Function `{}` expects {} argument(s), got {}",
+          name, min, arguments
+        )
+      }
+      (min, &usize::MAX) => format!(
+        "Function `{}` expects at least {} argument(s), got {}",
+        name, min, arguments
+      ),
+      (min, max) => format!(
+        "Function `{}` expects {} to {} arguments, got {}",
+        name, min, max, arguments
+      ),
+    });
+  }
+  Ok(())
+}
+
+/// Call a built-in function with its evaluated arguments, the geometry is only required by the
+/// spatial functions.
+pub fn call(
+  name: &str,
+  arguments: Vec<Predicate>,
+  geometry: Result<&JsonObject, String>,
+) -> Result<Predicate, String> {
+  check_function(name, arguments.len())?;
+  match name {
+    "intersects_bbox" | "within_bbox" | "contains_point" | "distance_km" => {
+      return call_spatial(name, as_numbers(name, &arguments)?, geometry?);
+    }
+    _ => (),
+  }
+  match (name, arguments.as_slice()) {
+    ("len", [Predicate::String(s)]) => Ok(Predicate::Number(s.chars().count() as f64)),
+    ("len", [Predicate::Array(array)]) => Ok(Predicate::Number(array.len() as f64)),
+    ("len", [Predicate::Object(object)]) => Ok(Predicate::Number(object.len() as f64)),
+    ("lower", [Predicate::String(s)]) => Ok(Predicate::String(s.to_lowercase())),
+    ("exists", [value]) => Ok(Predicate::Boolean(value != &Predicate::Null)),
+    ("coalesce", values) => Ok(
+      values
+        .iter()
+        .find(|value| *value != &Predicate::Null)
+        .cloned()
+        .unwrap_or(Predicate::Null),
+    ),
+    ("starts_with", [Predicate::Null, _]) => Ok(Predicate::Boolean(false)),
+    ("starts_with", [Predicate::String(s), Predicate::String(prefix)]) => {
+      Ok(Predicate::Boolean(s.starts_with(prefix.as_str())))
+    }
+    ("abs", [Predicate::Number(n)]) => Ok(Predicate::Number(n.abs())),
+    ("round", [Predicate::Number(n)]) => Ok(Predicate::Number(n.round())),
+    ("round", [Predicate::Number(n), Predicate::Number(digits)]) => {
+      let factor = 10_f64.powi(*digits as i32);
+      Ok(Predicate::Number((n * factor).round() / factor))
+    }
+    (_, [Predicate::Null, ..]) => Ok(Predicate::Null),
+    (name, arguments) => Err(format!(
+      "Invalid arguments for function `{}`: {:?}",
+      name, arguments
+    )),
+  }
+}
+
+fn as_numbers(name: &str, arguments: &[Predicate]) -> Result<Vec<f64>, String> {
+  arguments
+    .iter()
+    .map(|argument| match argument {
+      Predicate::Number(n) => Ok(*n),
+      _ => Err(format!(
+        "Function `{}` expects numbers, got {:?}",
+        name, argument
+      )),
+    })
+    .collect()
+}
+
+/// Spatial functions, coordinates are `lon, lat` and bbox are `minx, miny, maxx, maxy`.
+fn call_spatial(
+  name: &str,
+  arguments: Vec<f64>,
+  geometry: &JsonObject,
+) -> Result<Predicate, String> {
+  match name {
+    "intersects_bbox" => {
+      let bbox = geometry.compute_bbox();
+      Ok(Predicate::Boolean(
+        bbox[0] <= arguments[2]
+          && bbox[2] >= arguments[0]
+          && bbox[1] <= arguments[3]
+          && bbox[3] >= arguments[1],
+      ))
+    }
+    "within_bbox" => {
+      let bbox = geometry.compute_bbox();
+      Ok(Predicate::Boolean(
+        bbox[0] >= arguments[0]
+          && bbox[1] >= arguments[1]
+          && bbox[2] <= arguments[2]
+          && bbox[3] <= arguments[3],
+      ))
+    }
+    "contains_point" => Ok(Predicate::Boolean(geometry.contains_point(&arguments))),
+    "distance_km" => Ok(Predicate::Number(compute_distance_km(
+      geometry.compute_centroid(),
+      (arguments[0], arguments[1]),
+    ))),
+    name => Err(format!("Unknown function `{}`", name)),
+  }
+}
+
+#[cfg(test)]
+mod test_functions {
+  use super::*;
+
+  #[test]
+  fn check() {
+    assert!(check_function("len", 1).is_ok());
+    assert!(check_function("coalesce", 4).is_ok());
+    assert_eq!(
+      check_function("length", 1).unwrap_err(),
+      "Unknown function `length`"
+    );
+    assert_eq!(
+      check_function("len", 2).unwrap_err(),
+      "Function `len` expects 1 argument(s), got 2"
+    );
+    assert_eq!(
+      check_function("coalesce", 0).unwrap_err(),
+      "Function `coalesce` expects at least 1 argument(s), got 0"
+    );
+    assert_eq!(
+      check_function("round", 3).unwrap_err(),
+      "Function `round` expects 1 to 2 arguments, got 3"
+    );
+  }
+
+  const NO_GEOMETRY: Result<&JsonObject, String> = Err(String::new());
+
+  #[test]
+  fn call_functions() -> Result<(), String> {
+    let string = |s: &str| Predicate::String(s.to_string());
+    assert_eq!(
+      call("len", vec![string("Ajaccio")], NO_GEOMETRY)?,
+      Predicate::Number(7.0)
+    );
+    assert_eq!(
+      call(
+        "len",
+        vec![Predicate::Array(vec![string("fre")])],
+        NO_GEOMETRY
+      )?,
+      Predicate::Number(1.0)
+    );
+    assert_eq!(
+      call("len", vec![Predicate::Null], NO_GEOMETRY)?,
+      Predicate::Null
+    );
+    assert_eq!(
+      call("lower", vec![string("AJACCIO")], NO_GEOMETRY)?,
+      string("ajaccio")
+    );
+    assert_eq!(
+      call("exists", vec![Predicate::Null], NO_GEOMETRY)?,
+      Predicate::Boolean(false)
+    );
+    assert_eq!(
+      call(
+        "coalesce",
+        vec![Predicate::Null, Predicate::Number(1.0)],
+        NO_GEOMETRY
+      )?,
+      Predicate::Number(1.0)
+    );
+    assert_eq!(
+      call(
+        "starts_with",
+        vec![string("Ajaccio"), string("Aja")],
+        NO_GEOMETRY
+      )?,
+      Predicate::Boolean(true)
+    );
+    assert_eq!(
+      call("abs", vec![Predicate::Number(-1.5)], NO_GEOMETRY)?,
+      Predicate::Number(1.5)
+    );
+    assert_eq!(
+      call("round", vec![Predicate::Number(8.5857)], NO_GEOMETRY)?,
+      Predicate::Number(9.0)
+    );
+    assert_eq!(
+      call(
+        "round",
+        vec![Predicate::Number(8.5857), Predicate::Number(2.0)],
+        NO_GEOMETRY
+      )?,
+      Predicate::Number(8.59)
+    );
+    assert!(call("lower", vec![Predicate::Number(1.0)], NO_GEOMETRY).is_err());
+    assert!(call("abs", vec![string("1")], NO_GEOMETRY).is_err());
+    Ok(())
+  }
+}
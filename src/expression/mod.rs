@@ -1,9 +1,18 @@
 mod de;
 mod evaluate;
+mod functions;
+mod path;
+mod pattern;
 mod tokenizer;
+mod variables;
 
 use super::expression::de::parse;
+use crate::JsonValue;
+pub use de::parse_projection;
 pub use evaluate::Evaluate;
+pub use path::PathItem;
+pub use pattern::Pattern;
+use std::cmp::Ordering;
 use std::convert::TryFrom;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -14,20 +23,101 @@ pub enum Predicate {
   Not(Box<Predicate>),
   Eq(Box<Predicate>, Box<Predicate>),
   Neq(Box<Predicate>, Box<Predicate>),
+  Lt(Box<Predicate>, Box<Predicate>),
+  Lte(Box<Predicate>, Box<Predicate>),
+  Gt(Box<Predicate>, Box<Predicate>),
+  Gte(Box<Predicate>, Box<Predicate>),
+  Like(Box<Predicate>, Pattern),
+  ILike(Box<Predicate>, Pattern),
+  Match(Box<Predicate>, Pattern),
+  Contains(Box<Predicate>, Box<Predicate>),
+  Any(Box<Predicate>, Vec<Predicate>),
   Variable(String),
+  Path(Vec<PathItem>),
+  Function(String, Vec<Predicate>),
   String(String),
   Number(f64),
   Boolean(bool),
+  Array(Vec<Predicate>),
+  Object(Vec<(String, Predicate)>),
   Null,
 }
 
 impl Predicate {
+  /// Convert an evaluated value to JSON, operators are converted to null.
+  pub fn to_json(&self) -> JsonValue {
+    match self {
+      Predicate::String(s) => JsonValue::from(s.as_str()),
+      Predicate::Number(n) => JsonValue::from(*n),
+      Predicate::Boolean(b) => JsonValue::from(*b),
+      Predicate::Array(array) => JsonValue::Array(array.iter().map(Predicate::to_json).collect()),
+      Predicate::Object(object) => {
+        let mut json = JsonValue::new_object();
+        for (key, value) in object {
+          json[key.as_str()] = value.to_json();
+        }
+        json
+      }
+      _ => JsonValue::Null,
+    }
+  }
+
   fn as_bool(&self) -> Result<bool, String> {
     match self {
       Predicate::Boolean(b) => Ok(*b),
       _ => Err(format!("{:?} is not a boolean", self)),
     }
   }
+
+  /// Compare two evaluated values, numbers are compared numerically and strings lexicographically.
+  /// Returns `None` when one of the values is null, like SQL does.
+  fn compare(&self, other: &Predicate) -> Result<Option<Ordering>, String> {
+    match (self, other) {
+      (Predicate::Null, _) | (_, Predicate::Null) => Ok(None),
+      (Predicate::Number(a), Predicate::Number(b)) => Ok(a.partial_cmp(b)),
+      (Predicate::String(a), Predicate::String(b)) => Ok(Some(a.cmp(b))),
+      _ => Err(format!(
+        "Can't compare {:?} with {:?}, both must be numbers or strings",
+        self, other
+      )),
+    }
+  }
+
+  /// An array contains an element when one of its items is equal, a string contains a substring.
+  fn contains(&self, value: &Predicate) -> Result<bool, String> {
+    match (self, value) {
+      (Predicate::Null, _) => Ok(false),
+      (Predicate::Array(array), _) => Ok(array.contains(value)),
+      (Predicate::String(s), Predicate::String(substring)) => Ok(s.contains(substring.as_str())),
+      _ => Err(format!(
+        "Can't check if {:?} contains {:?}, it must be an array or a string",
+        self, value
+      )),
+    }
+  }
+
+  /// True when at least one element of the array is in the list, a single value is handled as an
+  /// array of one element.
+  fn contains_any(&self, list: &[Predicate]) -> bool {
+    match self {
+      Predicate::Null => false,
+      Predicate::Array(array) => array.iter().any(|element| list.contains(element)),
+      value => list.contains(value),
+    }
+  }
+
+  /// Match an evaluated value against a pattern, null never matches.
+  fn is_match(&self, pattern: &Pattern) -> Result<bool, String> {
+    match self {
+      Predicate::Null => Ok(false),
+      Predicate::String(s) => Ok(pattern.is_match(s)),
+      _ => Err(format!(
+        "Can't match {:?} with pattern `{}`, it must be a string",
+        self,
+        pattern.as_str()
+      )),
+    }
+  }
 }
 
 impl TryFrom<String> for Predicate {
@@ -102,4 +192,26 @@ mod test_expression {
     }
     Ok(())
   }
+
+  #[test]
+  fn predicate_to_json() {
+    assert_eq!(Predicate::Number(101748927.0).to_json().dump(), "101748927");
+    assert_eq!(
+      Predicate::String("Ajaccio".to_string()).to_json().dump(),
+      "\"Ajaccio\""
+    );
+    assert_eq!(
+      Predicate::Object(vec![(
+        "gn:id".to_string(),
+        Predicate::Array(vec![Predicate::Boolean(true), Predicate::Null])
+      )])
+      .to_json()
+      .dump(),
+      "{\"gn:id\":[true,null]}"
+    );
+    assert_eq!(
+      Predicate::Not(Box::new(Predicate::Boolean(true))).to_json(),
+      JsonValue::Null
+    );
+  }
 }
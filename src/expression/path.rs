@@ -0,0 +1,150 @@
+use crate::{JsonObject, JsonValue};
+
+/// One step of a property path such as `wof:hierarchy[0].country_id`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathItem {
+  /// Key of an object, `wof:concordances` or `gn:id` in `wof:concordances.gn:id`.
+  Key(String),
+  /// Index of an array, `0` in `name:fra_x_preferred[0]`.
+  Index(usize),
+}
+
+/// Returns true when the variable must be read as a path and not as a single property.
+pub fn is_path(variable: &str) -> bool {
+  variable.contains('.') || variable.contains('[')
+}
+
+/// Split a property path, `.` separates object keys and `[n]` selects an element of an array.
+pub fn parse_path(path: &str) -> Result<Vec<PathItem>, String> {
+  let mut items: Vec<PathItem> = vec![];
+  let mut key = String::new();
+  let mut chars = path.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      '.' | '[' => {
+        if !key.is_empty() {
+          items.push(PathItem::Key(key));
+          key = String::new();
+        } else if c == '.' || items.is_empty() {
+          return Err(format!("Empty key in path `{}`", path));
+        }
+        if c == '[' {
+          let mut index = String::new();
+          loop {
+            match chars.next() {
+              Some(']') => break,
+              Some(c) if c.is_ascii_digit() => index.push(c),
+              _ => return Err(format!("Invalid array index in path `{}`", path)),
+            }
+          }
+          items.push(PathItem::Index(
+            index
+              .parse::<usize>()
+              .map_err(|_| format!("Invalid array index in path `{}`", path))?,
+          ));
+          match chars.peek() {
+            Some('.') => {
+              chars.next();
+              if chars.peek().is_none() {
+                return Err(format!("Empty key in path `{}`", path));
+              }
+            }
+            Some('[') | None => (),
+            Some(_) => return Err(format!("Expected `.` or `[` after `]` in path `{}`", path)),
+          }
+        }
+      }
+      ']' => return Err(format!("Unexpected `]` in path `{}`", path)),
+      c => key.push(c),
+    }
+  }
+  if !key.is_empty() {
+    items.push(PathItem::Key(key));
+  } else if path.ends_with('.') {
+    return Err(format!("Empty key in path `{}`", path));
+  }
+  Ok(items)
+}
+
+/// Follow the path from the properties object, returns `None` when an element is missing.
+pub fn resolve_path<'a>(properties: &'a JsonObject, path: &[PathItem]) -> Option<&'a JsonValue> {
+  let (first, rest) = path.split_first()?;
+  let mut value = match first {
+    PathItem::Key(key) => properties.get(key)?,
+    PathItem::Index(_) => return None,
+  };
+  for item in rest {
+    value = match (item, value) {
+      (PathItem::Key(key), JsonValue::Object(object)) => object.get(key)?,
+      (PathItem::Index(index), JsonValue::Array(array)) => array.get(*index)?,
+      _ => return None,
+    };
+  }
+  Some(value)
+}
+
+#[cfg(test)]
+mod test_path {
+  use super::*;
+  use crate::utils::JsonUtils;
+  use json::object;
+
+  #[test]
+  fn parse() -> Result<(), String> {
+    assert_eq!(
+      parse_path("wof:hierarchy[0].country_id")?,
+      vec![
+        PathItem::Key("wof:hierarchy".to_string()),
+        PathItem::Index(0),
+        PathItem::Key("country_id".to_string())
+      ]
+    );
+    assert_eq!(
+      parse_path("wof:concordances.gn:id")?,
+      vec![
+        PathItem::Key("wof:concordances".to_string()),
+        PathItem::Key("gn:id".to_string())
+      ]
+    );
+    assert_eq!(
+      parse_path("a[1][12]")?,
+      vec![
+        PathItem::Key("a".to_string()),
+        PathItem::Index(1),
+        PathItem::Index(12)
+      ]
+    );
+    for path in [
+      "[0]", "a..b", "a.", ".a", "a[]", "a[x]", "a[0", "a[0]b", "a]",
+    ] {
+      assert!(parse_path(path).is_err(), "{}", path);
+    }
+    Ok(())
+  }
+
+  #[test]
+  fn resolve() -> Result<(), String> {
+    let json = object! {
+      "wof:hierarchy" => vec![object!{ "country_id" => 85633147 }],
+      "wof:concordances" => object!{ "gn:id" => 935266 },
+    };
+    let properties = json.as_object().unwrap();
+    assert_eq!(
+      resolve_path(properties, &parse_path("wof:hierarchy[0].country_id")?),
+      Some(&JsonValue::from(85633147))
+    );
+    assert_eq!(
+      resolve_path(properties, &parse_path("wof:concordances.gn:id")?),
+      Some(&JsonValue::from(935266))
+    );
+    assert_eq!(
+      resolve_path(properties, &parse_path("wof:hierarchy[1].country_id")?),
+      None
+    );
+    assert_eq!(
+      resolve_path(properties, &parse_path("wof:concordances[0]")?),
+      None
+    );
+    Ok(())
+  }
+}
@@ -0,0 +1,84 @@
+use regex::{Regex, RegexBuilder};
+
+/// Compiled pattern of `LIKE`, `ILIKE` and `~` operators.
+/// The regex is built once when the expression is parsed and reused for every evaluated document.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+  source: String,
+  regex: Regex,
+}
+
+impl Pattern {
+  /// SQL `LIKE` pattern, `%` matches any sequence of characters and `_` matches one character.
+  pub fn like(pattern: &str, case_insensitive: bool) -> Result<Self, String> {
+    let mut regex = String::from("^");
+    for c in pattern.chars() {
+      match c {
+        '%' => regex.push_str(".*"),
+        '_' => regex.push('.'),
+        c => regex.push_str(&regex::escape(&c.to_string())),
+      }
+    }
+    regex.push('$');
+    Ok(Pattern {
+      source: pattern.to_string(),
+      regex: RegexBuilder::new(&regex)
+        .case_insensitive(case_insensitive)
+        .dot_matches_new_line(true)
+        .build()
+        .map_err(|e| format!("Invalid like pattern `{}`: {}", pattern, e))?,
+    })
+  }
+
+  /// Regular expression pattern, matches when any part of the value matches.
+  pub fn regex(pattern: &str) -> Result<Self, String> {
+    Ok(Pattern {
+      source: pattern.to_string(),
+      regex: Regex::new(pattern)
+        .map_err(|e| format!("Invalid regular expression `{}`: {}", pattern, e))?,
+    })
+  }
+
+  pub fn is_match(&self, value: &str) -> bool {
+    self.regex.is_match(value)
+  }
+
+  /// The pattern as written in the expression.
+  pub fn as_str(&self) -> &str {
+    &self.source
+  }
+}
+
+impl PartialEq for Pattern {
+  fn eq(&self, other: &Self) -> bool {
+    self.source == other.source && self.regex.as_str() == other.regex.as_str()
+  }
+}
+
+#[cfg(test)]
+mod test_pattern {
+  use super::*;
+
+  #[test]
+  fn like() -> Result<(), String> {
+    let pattern = Pattern::like("Saint-%", false)?;
+    assert!(pattern.is_match("Saint-Benoît"));
+    assert!(pattern.is_match("Saint-"));
+    assert!(!pattern.is_match("saint-Denis"));
+    assert!(!pattern.is_match("Le Saint-Denis"));
+    assert!(Pattern::like("saint-%", true)?.is_match("Saint-Denis"));
+    assert!(Pattern::like("P_r.s", false)?.is_match("P\nr.s"));
+    assert!(!Pattern::like("P_r.s", false)?.is_match("Paris"));
+    Ok(())
+  }
+
+  #[test]
+  fn regex() -> Result<(), String> {
+    let pattern = Pattern::regex(r"^San\s")?;
+    assert!(pattern.is_match("San Francisco"));
+    assert!(!pattern.is_match("Santa Cruz"));
+    assert!(Pattern::regex("cisco")?.is_match("San Francisco"));
+    assert!(Pattern::regex("(").is_err());
+    Ok(())
+  }
+}
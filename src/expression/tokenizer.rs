@@ -1,20 +1,27 @@
 use super::Predicate;
-use regex::Regex;
 use std::convert::TryInto;
 
-lazy_static! {
-  static ref END_QUOTE_REGEX: Regex = Regex::new("(('')+'$)|([^']'$)|(^'$)").unwrap();
-  static ref NUMBER_REGEX: Regex = Regex::new("^-?[0-9]+(\\.[0-9]*)?$").unwrap();
-}
-
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
   Eq,
   Neq,
+  Lt,
+  Lte,
+  Gt,
+  Gte,
   And,
   Or,
   In,
   Not,
+  Like,
+  ILike,
+  Match,
+  NotMatch,
+  Contains,
+  Any,
+  LeftParenthesis,
+  RightParenthesis,
+  Comma,
   Variable(String),
   String(String),
   Number(f64),
@@ -22,55 +29,160 @@ pub enum Token {
   Null,
 }
 
-pub fn tokenize(predicate: String) -> Vec<Token> {
-  let mut tokens: Vec<Token> = vec![];
-  let clauses: Vec<&str> = predicate.split(" ").collect();
+/// A token with its position in the expression, used to report syntax errors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lexeme {
+  pub token: Token,
+  /// Column of the first character of the token, starting at 1.
+  pub column: usize,
+  /// The token as written in the expression.
+  pub text: String,
+}
+
+/// Split the expression in tokens, character by character.
+pub fn tokenize(expression: &str) -> Result<Vec<Lexeme>, String> {
+  let chars: Vec<char> = expression.chars().collect();
+  let mut lexemes: Vec<Lexeme> = vec![];
   let mut i = 0;
 
-  while i < clauses.len() {
-    match clauses[i].to_lowercase().as_str() {
-      "=" | "==" => tokens.push(Token::Eq),
-      "!=" | "<>" => tokens.push(Token::Neq),
-      "and" | "&&" => tokens.push(Token::And),
-      "or" | "||" => tokens.push(Token::Or),
-      "not" => tokens.push(Token::Not),
-      "in" => tokens.push(Token::In),
-      "true" => tokens.push(Token::Boolean(true)),
-      "false" => tokens.push(Token::Boolean(false)),
-      "null" => tokens.push(Token::Null),
-      _ => {
-        if clauses[i].starts_with("'") {
-          if clauses[i].ends_with("'") && clauses[i] != "'" {
-            let mut string = clauses[i].to_string();
-            string.pop();
-            string.remove(0);
-            tokens.push(Token::String(string.replace("''", "'")));
-          } else {
-            let mut string = clauses[i].to_string();
-            string.remove(0);
-            string = string.replace("''", "'");
-            loop {
-              i = i + 1;
-              if i >= clauses.len() || END_QUOTE_REGEX.is_match(clauses[i]) {
-                let mut s = clauses[i].to_string();
-                s.pop();
-                string = format!("{} {}", string, s.replace("''", "'"));
-                break;
-              }
-              string = format!("{} {}", string, clauses[i].replace("''", "'"));
+  while i < chars.len() {
+    let c = chars[i];
+    let next = chars.get(i + 1).copied();
+    let start = i;
+    let token = match c {
+      c if c.is_whitespace() => {
+        i += 1;
+        continue;
+      }
+      '(' => Token::LeftParenthesis,
+      ')' => Token::RightParenthesis,
+      ',' => Token::Comma,
+      '=' if next == Some('=') => {
+        i += 1;
+        Token::Eq
+      }
+      '=' => Token::Eq,
+      '!' if next == Some('=') => {
+        i += 1;
+        Token::Neq
+      }
+      '!' if next == Some('~') => {
+        i += 1;
+        Token::NotMatch
+      }
+      '~' => Token::Match,
+      '<' if next == Some('>') => {
+        i += 1;
+        Token::Neq
+      }
+      '<' if next == Some('=') => {
+        i += 1;
+        Token::Lte
+      }
+      '<' => Token::Lt,
+      '>' if next == Some('=') => {
+        i += 1;
+        Token::Gte
+      }
+      '>' => Token::Gt,
+      '&' if next == Some('&') => {
+        i += 1;
+        Token::And
+      }
+      '|' if next == Some('|') => {
+        i += 1;
+        Token::Or
+      }
+      '\'' => {
+        let mut string = String::new();
+        loop {
+          i += 1;
+          match chars.get(i) {
+            Some('\'') if chars.get(i + 1) == Some(&'\'') => {
+              string.push('\'');
+              i += 1;
             }
-            tokens.push(Token::String(string));
+            Some('\'') => break,
+            Some(c) => string.push(*c),
+            None => return Err(syntax_error(expression, start + 1, "Unterminated string")),
           }
-        } else if NUMBER_REGEX.is_match(clauses[i]) {
-          tokens.push(Token::Number(clauses[i].parse::<f64>().unwrap()));
-        } else {
-          tokens.push(Token::Variable(clauses[i].to_string()))
         }
+        Token::String(string)
       }
-    }
-    i = i + 1;
+      c if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) => {
+        while chars.get(i + 1).is_some_and(|n| n.is_ascii_digit()) {
+          i += 1;
+        }
+        if chars.get(i + 1) == Some(&'.') {
+          i += 1;
+          while chars.get(i + 1).is_some_and(|n| n.is_ascii_digit()) {
+            i += 1;
+          }
+        }
+        let number: String = chars[start..=i].iter().collect();
+        Token::Number(number.parse::<f64>().map_err(|_| {
+          syntax_error(
+            expression,
+            start + 1,
+            &format!("Invalid number `{}`", number),
+          )
+        })?)
+      }
+      c if is_identifier_start(c) => {
+        while chars.get(i + 1).is_some_and(|n| is_identifier_part(*n)) {
+          i += 1;
+        }
+        let identifier: String = chars[start..=i].iter().collect();
+        match identifier.to_lowercase().as_str() {
+          "and" => Token::And,
+          "or" => Token::Or,
+          "not" => Token::Not,
+          "in" => Token::In,
+          "like" => Token::Like,
+          "ilike" => Token::ILike,
+          "contains" => Token::Contains,
+          "any" => Token::Any,
+          "true" => Token::Boolean(true),
+          "false" => Token::Boolean(false),
+          "null" => Token::Null,
+          _ => Token::Variable(identifier),
+        }
+      }
+      c => {
+        return Err(syntax_error(
+          expression,
+          start + 1,
+          &format!("Unexpected character `{}`", c),
+        ))
+      }
+    };
+    i += 1;
+    lexemes.push(Lexeme {
+      token,
+      column: start + 1,
+      text: chars[start..i].iter().collect(),
+    });
   }
-  tokens
+  Ok(lexemes)
+}
+
+fn is_identifier_start(c: char) -> bool {
+  c.is_alphabetic() || c == '_' || c == '$'
+}
+
+fn is_identifier_part(c: char) -> bool {
+  c.is_alphanumeric() || matches!(c, '_' | ':' | '-' | '.' | '[' | ']')
+}
+
+/// Format an error message pointing to the column of the expression with a caret.
+pub fn syntax_error(expression: &str, column: usize, message: &str) -> String {
+  format!(
+    "{} at column {}\n{}\n{}^",
+    message,
+    column,
+    expression,
+    " ".repeat(column.saturating_sub(1))
+  )
 }
 
 impl TryInto<Predicate> for Token {
@@ -92,11 +204,19 @@ impl TryInto<Predicate> for Token {
 mod test_tokenizer {
   use super::*;
 
+  fn tokens(expression: String) -> Vec<Token> {
+    tokenize(&expression)
+      .unwrap()
+      .into_iter()
+      .map(|lexeme| lexeme.token)
+      .collect()
+  }
+
   #[test]
   fn tokenize_operators() {
     vec!["=", "=="].iter().for_each(|eq| {
       assert_eq!(
-        tokenize(format!("variable {} 'true'", eq)),
+        tokens(format!("variable {} 'true'", eq)),
         vec![
           Token::Variable("variable".to_string()),
           Token::Eq,
@@ -106,7 +226,7 @@ mod test_tokenizer {
     });
     vec!["!=", "<>"].iter().for_each(|neq| {
       assert_eq!(
-        tokenize(format!("variable {} 'true'", neq)),
+        tokens(format!("variable {} 'true'", neq)),
         vec![
           Token::Variable("variable".to_string()),
           Token::Neq,
@@ -114,9 +234,26 @@ mod test_tokenizer {
         ]
       )
     });
+    [
+      ("<", Token::Lt),
+      ("<=", Token::Lte),
+      (">", Token::Gt),
+      (">=", Token::Gte),
+    ]
+    .iter()
+    .for_each(|(op, token)| {
+      assert_eq!(
+        tokens(format!("variable {} 10", op)),
+        vec![
+          Token::Variable("variable".to_string()),
+          token.clone(),
+          Token::Number(10.0)
+        ]
+      )
+    });
     vec!["and", "&&"].iter().for_each(|neq| {
       assert_eq!(
-        tokenize(format!("variable {} 'true'", neq)),
+        tokens(format!("variable {} 'true'", neq)),
         vec![
           Token::Variable("variable".to_string()),
           Token::And,
@@ -126,24 +263,59 @@ mod test_tokenizer {
     });
     vec!["or", "||"].iter().for_each(|neq| {
       assert_eq!(
-        tokenize(format!("true {} false", neq)),
+        tokens(format!("true {} false", neq)),
         vec![Token::Boolean(true), Token::Or, Token::Boolean(false)]
       )
     });
     assert_eq!(
-      tokenize(format!("not true")),
+      tokens(format!("not true")),
       vec![Token::Not, Token::Boolean(true),]
     );
     assert_eq!(
-      tokenize(format!("in true")),
+      tokens(format!("in true")),
       vec![Token::In, Token::Boolean(true),]
     );
+    assert_eq!(
+      tokens("a like 'x%' AND b ILIKE 'y' or c ~ 'z' and d !~ 'w'".to_string()),
+      vec![
+        Token::Variable("a".to_string()),
+        Token::Like,
+        Token::String("x%".to_string()),
+        Token::And,
+        Token::Variable("b".to_string()),
+        Token::ILike,
+        Token::String("y".to_string()),
+        Token::Or,
+        Token::Variable("c".to_string()),
+        Token::Match,
+        Token::String("z".to_string()),
+        Token::And,
+        Token::Variable("d".to_string()),
+        Token::NotMatch,
+        Token::String("w".to_string()),
+      ]
+    );
+    assert_eq!(
+      tokens("variable not in ('a',1, 'b, c')".to_string()),
+      vec![
+        Token::Variable("variable".to_string()),
+        Token::Not,
+        Token::In,
+        Token::LeftParenthesis,
+        Token::String("a".to_string()),
+        Token::Comma,
+        Token::Number(1.0),
+        Token::Comma,
+        Token::String("b, c".to_string()),
+        Token::RightParenthesis,
+      ]
+    );
   }
 
   #[test]
   fn tokenize_literal() {
     assert_eq!(
-      tokenize(format!("variable = 'string'")),
+      tokens(format!("variable = 'string'")),
       vec![
         Token::Variable("variable".to_string()),
         Token::Eq,
@@ -151,7 +323,7 @@ mod test_tokenizer {
       ]
     );
     assert_eq!(
-      tokenize(format!("variable = '''string'''")),
+      tokens(format!("variable = '''string'''")),
       vec![
         Token::Variable("variable".to_string()),
         Token::Eq,
@@ -159,7 +331,7 @@ mod test_tokenizer {
       ]
     );
     assert_eq!(
-      tokenize(format!("variable = 'string with many words'")),
+      tokens(format!("variable = 'string with many words'")),
       vec![
         Token::Variable("variable".to_string()),
         Token::Eq,
@@ -167,7 +339,7 @@ mod test_tokenizer {
       ]
     );
     assert_eq!(
-      tokenize(format!("variable = '''string with many quotes'''")),
+      tokens(format!("variable = '''string with many quotes'''")),
       vec![
         Token::Variable("variable".to_string()),
         Token::Eq,
@@ -175,7 +347,7 @@ mod test_tokenizer {
       ]
     );
     assert_eq!(
-      tokenize(format!("variable = ' string with'' '' quotes inside '")),
+      tokens(format!("variable = ' string with'' '' quotes inside '")),
       vec![
         Token::Variable("variable".to_string()),
         Token::Eq,
@@ -183,7 +355,7 @@ mod test_tokenizer {
       ]
     );
     assert_eq!(
-      tokenize(format!("variable = true")),
+      tokens(format!("variable = true")),
       vec![
         Token::Variable("variable".to_string()),
         Token::Eq,
@@ -193,7 +365,7 @@ mod test_tokenizer {
 
     for elem in vec![-1.90, 1.90, 0.0, 0.90, 1234.5678] {
       assert_eq!(
-        tokenize(format!("variable = {}", elem)),
+        tokens(format!("variable = {}", elem)),
         vec![
           Token::Variable("variable".to_string()),
           Token::Eq,
@@ -204,7 +376,7 @@ mod test_tokenizer {
 
     for elem in vec![1, 2, -1, -100] {
       assert_eq!(
-        tokenize(format!("variable = {}", elem)),
+        tokens(format!("variable = {}", elem)),
         vec![
           Token::Variable("variable".to_string()),
           Token::Eq,
@@ -213,4 +385,77 @@ mod test_tokenizer {
       );
     }
   }
+
+  #[test]
+  fn tokenize_without_spaces() {
+    assert_eq!(
+      tokens("(a='x'||b>=2)&&not\tc<>-3.5".to_string()),
+      vec![
+        Token::LeftParenthesis,
+        Token::Variable("a".to_string()),
+        Token::Eq,
+        Token::String("x".to_string()),
+        Token::Or,
+        Token::Variable("b".to_string()),
+        Token::Gte,
+        Token::Number(2.0),
+        Token::RightParenthesis,
+        Token::And,
+        Token::Not,
+        Token::Variable("c".to_string()),
+        Token::Neq,
+        Token::Number(-3.5),
+      ]
+    );
+  }
+
+  #[test]
+  fn tokenize_paths() {
+    assert_eq!(
+      tokens("wof:hierarchy[0].country_id = 1 and wof:belongsto contains 2".to_string()),
+      vec![
+        Token::Variable("wof:hierarchy[0].country_id".to_string()),
+        Token::Eq,
+        Token::Number(1.0),
+        Token::And,
+        Token::Variable("wof:belongsto".to_string()),
+        Token::Contains,
+        Token::Number(2.0),
+      ]
+    );
+    assert_eq!(
+      tokens("wof:lang ANY ('fre')".to_string()),
+      vec![
+        Token::Variable("wof:lang".to_string()),
+        Token::Any,
+        Token::LeftParenthesis,
+        Token::String("fre".to_string()),
+        Token::RightParenthesis,
+      ]
+    );
+  }
+
+  #[test]
+  fn tokenize_columns() {
+    let lexemes = tokenize("wof:name  = 'Paris'").unwrap();
+    assert_eq!(
+      lexemes
+        .iter()
+        .map(|lexeme| (lexeme.column, lexeme.text.as_str()))
+        .collect::<Vec<(usize, &str)>>(),
+      vec![(1, "wof:name"), (11, "="), (13, "'Paris'")]
+    );
+  }
+
+  #[test]
+  fn tokenize_errors() {
+    assert_eq!(
+      tokenize("wof:name = 'Paris").unwrap_err(),
+      "Unterminated string at column 12\nwof:name = 'Paris\n           ^"
+    );
+    assert_eq!(
+      tokenize("wof:id = #1").unwrap_err(),
+      "Unexpected character `#` at column 10\nwof:id = #1\n         ^"
+    );
+  }
 }
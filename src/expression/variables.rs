@@ -0,0 +1,64 @@
+use super::Predicate;
+use crate::utils::GeoCompute;
+use crate::wof::WOFGeoJSON;
+
+/// Reserved variables computed from the WOF document, they start with `$`.
+const VARIABLES: [&str; 18] = [
+  "$id",
+  "$is_current",
+  "$is_deprecated",
+  "$is_superseded",
+  "$is_superseding",
+  "$is_ceased",
+  "$is_alt_geom",
+  "$placetype",
+  "$name",
+  "$country",
+  "$repo",
+  "$source",
+  "$lat",
+  "$lon",
+  "$parent_id",
+  "$lastmodified",
+  "$area",
+  "$area_square_m",
+];
+
+/// Returns true for reserved variables such as `$is_current`.
+pub fn is_wof_variable(name: &str) -> bool {
+  name.starts_with('$')
+}
+
+/// Check that the reserved variable exists.
+pub fn check_wof_variable(name: &str) -> Result<(), String> {
+  if VARIABLES.contains(&name) {
+    Ok(())
+  } else {
+    Err(format!("Unknown variable `{}`", name))
+  }
+}
+
+/// Compute the value of a reserved variable with the [`WOFGeoJSON`] accessors.
+pub fn get_wof_variable(wof_obj: &WOFGeoJSON, name: &str) -> Result<Predicate, String> {
+  match name {
+    "$id" => Ok(Predicate::Number(wof_obj.id as f64)),
+    "$is_current" => Ok(Predicate::Boolean(wof_obj.is_current())),
+    "$is_deprecated" => Ok(Predicate::Boolean(wof_obj.is_deprecated())),
+    "$is_superseded" => Ok(Predicate::Boolean(wof_obj.is_superseded())),
+    "$is_superseding" => Ok(Predicate::Boolean(wof_obj.is_superseding())),
+    "$is_ceased" => Ok(Predicate::Boolean(wof_obj.is_ceased())),
+    "$is_alt_geom" => Ok(Predicate::Boolean(wof_obj.is_alt_geom())),
+    "$placetype" => Ok(Predicate::String(wof_obj.get_placetype())),
+    "$name" => Ok(Predicate::String(wof_obj.get_name())),
+    "$country" => Ok(Predicate::String(wof_obj.get_country())),
+    "$repo" => Ok(Predicate::String(wof_obj.get_repo())),
+    "$source" => Ok(Predicate::String(wof_obj.get_source())),
+    "$lat" => Ok(Predicate::Number(wof_obj.get_lat())),
+    "$lon" => Ok(Predicate::Number(wof_obj.get_lon())),
+    "$parent_id" => Ok(Predicate::Number(wof_obj.get_parent_id() as f64)),
+    "$lastmodified" => Ok(Predicate::Number(wof_obj.get_last_modified() as f64)),
+    "$area" => Ok(Predicate::Number(wof_obj.geometry.compute_area())),
+    "$area_square_m" => Ok(Predicate::Number(wof_obj.geometry.compute_area_m())),
+    name => Err(format!("Unknown variable `{}`", name)),
+  }
+}
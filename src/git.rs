@@ -1,6 +1,6 @@
 use crate::utils::ResultExit;
-use git2::{DiffFormat, DiffLine, ObjectType, Repository};
-use std::path::PathBuf;
+use git2::{DiffFormat, DiffLine, ObjectType, Repository, Tree};
+use std::path::{Path, PathBuf};
 use std::vec::Vec;
 
 pub struct Git {
@@ -9,8 +9,13 @@ pub struct Git {
 
 impl Git {
   pub fn new() -> Git {
+    Git::open(".")
+  }
+
+  /// Open the git repository containing the path.
+  pub fn open<P: AsRef<Path>>(path: P) -> Git {
     return Git {
-      workdir: Repository::discover(".")
+      workdir: Repository::discover(path)
         .expect_exit("This is not a git repository. Make sure to be in a git folder")
         .workdir()
         .expect("Git working directory not found. Should not happen.")
@@ -36,11 +41,31 @@ impl Git {
 
     let tree_src = obj_src.tree().expect("Can't get the source tree.");
     let tree_dst = obj_dst
-      .peel(ObjectType::Tree)
+      .peel_to_tree()
       .expect("Can't get the destination tree.");
+    self.diff_trees(&repo, &tree_src, &tree_dst)
+  }
+
+  /// Paths changed between two commits, removed and renamed paths are included.
+  pub fn get_changes_from_range(&self, from: &String, to: &String) -> Vec<PathBuf> {
+    let repo = self.repository();
+    let tree_src = repo
+      .revparse_single(from.as_str())
+      .expect_exit(format!("Commit/branch {} not found", from).as_str())
+      .peel_to_tree()
+      .expect_exit(format!("Can't get the tree of {}", from).as_str());
+    let tree_dst = repo
+      .revparse_single(to.as_str())
+      .expect_exit(format!("Commit/branch {} not found", to).as_str())
+      .peel_to_tree()
+      .expect_exit(format!("Can't get the tree of {}", to).as_str());
+    self.diff_trees(&repo, &tree_src, &tree_dst)
+  }
+
+  fn diff_trees(&self, repo: &Repository, tree_src: &Tree, tree_dst: &Tree) -> Vec<PathBuf> {
     let mut paths: Vec<PathBuf> = Vec::new();
     repo
-      .diff_tree_to_tree(Some(&tree_src), tree_dst.as_tree(), None)
+      .diff_tree_to_tree(Some(tree_src), Some(tree_dst), None)
       .expect("Can't create the diff between source and destination")
       .print(DiffFormat::NameOnly, |_delta, _hunk, line| {
         paths.push(self.diff_line_to_real_path(line));
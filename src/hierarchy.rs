@@ -0,0 +1,194 @@
+//! Rebuild the parent and the hierarchies of documents from the polygons containing them.
+use crate::export::export_belongsto;
+use crate::pip::PipIndex;
+use crate::placetypes::{can_be_parent, get_rank};
+use crate::wof::WOFGeoJSON;
+use crate::JsonValue;
+use std::collections::HashMap;
+
+/// The new parent and hierarchies of a document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolution {
+  pub parent_id: i64,
+  pub hierarchies: Vec<JsonValue>,
+}
+
+impl Resolution {
+  /// Rewrite `wof:parent_id`, `wof:hierarchy` and `wof:belongsto` of the document.
+  pub fn apply(&self, json: &mut JsonValue) -> Result<(), String> {
+    let id = json["id"]
+      .as_i64()
+      .ok_or("Top level `id` is not a number")?;
+    let properties = match &mut json["properties"] {
+      JsonValue::Object(properties) => properties,
+      _ => return Err("`properties` key must be an object".to_string()),
+    };
+    properties.insert("wof:parent_id", JsonValue::from(self.parent_id));
+    properties.insert("wof:hierarchy", JsonValue::from(self.hierarchies.clone()));
+    export_belongsto(properties, id);
+    Ok(())
+  }
+}
+
+/// Finds the parent of a document with the polygons containing its centroid.
+#[derive(Debug, Default)]
+pub struct HierarchyResolver {
+  index: PipIndex,
+  hierarchies: HashMap<i64, Vec<JsonValue>>,
+}
+
+impl HierarchyResolver {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Add a candidate parent, returns false when it's not a current polygon.
+  pub fn add(&mut self, json: &JsonValue) -> Result<bool, String> {
+    let wof_obj = WOFGeoJSON::as_valid_wof_geojson(json)?;
+    if wof_obj.is_doc_deprecated() || !self.index.add(json)? {
+      return Ok(false);
+    }
+    let id = json["id"].as_i64().unwrap_or(wof_obj.id as i64);
+    self.hierarchies.insert(id, get_hierarchies(json));
+    Ok(true)
+  }
+
+  pub fn len(&self) -> usize {
+    self.index.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.index.is_empty()
+  }
+
+  /// Replace the hierarchies of a candidate, used once its own hierarchy is rebuilt.
+  pub fn update(&mut self, id: i64, resolution: &Resolution) {
+    if let Some(hierarchies) = self.hierarchies.get_mut(&id) {
+      *hierarchies = resolution.hierarchies.clone();
+    }
+  }
+
+  /// Returns the new parent and hierarchies of a document, `None` when the placetype is unknown
+  /// or when no polygon of a parent placetype contains its centroid.
+  pub fn resolve(&self, json: &JsonValue) -> Result<Option<Resolution>, String> {
+    let wof_obj = WOFGeoJSON::as_valid_wof_geojson(json)?;
+    let id = json["id"].as_i64().unwrap_or(wof_obj.id as i64);
+    let placetype = wof_obj.get_placetype();
+    if get_rank(&placetype).is_none() {
+      return Ok(None);
+    }
+    let candidates: Vec<_> = self
+      .index
+      .lookup(wof_obj.get_lon(), wof_obj.get_lat())
+      .into_iter()
+      .filter(|candidate| candidate.id != id && get_rank(&candidate.placetype).is_some())
+      .filter(|candidate| can_be_parent(&candidate.placetype, &placetype))
+      .collect();
+    // Candidates are sorted from the largest placetype, the parents are the closest ones.
+    let rank = match candidates.last() {
+      Some(candidate) => get_rank(&candidate.placetype),
+      None => return Ok(None),
+    };
+    let parents: Vec<_> = candidates
+      .iter()
+      .filter(|candidate| get_rank(&candidate.placetype) == rank)
+      .collect();
+    let key = format!("{}_id", placetype);
+    let mut hierarchies: Vec<JsonValue> = vec![];
+    for parent in &parents {
+      let parent_hierarchies = match self.hierarchies.get(&parent.id) {
+        Some(parent_hierarchies) if !parent_hierarchies.is_empty() => parent_hierarchies.clone(),
+        _ => {
+          let mut hierarchy = JsonValue::new_object();
+          hierarchy[format!("{}_id", parent.placetype).as_str()] = parent.id.into();
+          vec![hierarchy]
+        }
+      };
+      for mut hierarchy in parent_hierarchies {
+        hierarchy[key.as_str()] = id.into();
+        if !hierarchies.contains(&hierarchy) {
+          hierarchies.push(hierarchy);
+        }
+      }
+    }
+    Ok(Some(Resolution {
+      parent_id: parents[0].id,
+      hierarchies,
+    }))
+  }
+}
+
+fn get_hierarchies(json: &JsonValue) -> Vec<JsonValue> {
+  json["properties"]["wof:hierarchy"]
+    .members()
+    .filter(|hierarchy| hierarchy.is_object())
+    .cloned()
+    .collect()
+}
+
+#[cfg(test)]
+mod test_hierarchy {
+  use super::*;
+  use json::object;
+
+  fn square(id: i64, placetype: &str, min: f64, max: f64, hierarchy: JsonValue) -> JsonValue {
+    object! {
+      "id" => id,
+      "type" => "Feature",
+      "properties" => object!{
+        "wof:id" => id,
+        "wof:placetype" => placetype,
+        "wof:parent_id" => -1,
+        "wof:hierarchy" => vec![hierarchy],
+      },
+      "geometry" => object!{
+        "coordinates" => vec![vec![vec![min, min], vec![max, min], vec![max, max], vec![min, max], vec![min, min]]],
+        "type" => "Polygon"
+      },
+      "bbox" => vec![min, min, max, max],
+    }
+  }
+
+  #[test]
+  fn resolve() {
+    let mut resolver = HierarchyResolver::new();
+    let country = square(1, "country", 0.0, 10.0, object! { "country_id" => 1 });
+    let region = square(
+      2,
+      "region",
+      1.0,
+      5.0,
+      object! { "country_id" => 7, "region_id" => 2 },
+    );
+    let mut deprecated = square(3, "county", 1.0, 5.0, object! {});
+    deprecated["properties"]["mz:is_current"] = 0.into();
+    for json in [&country, &region, &deprecated] {
+      resolver.add(json).unwrap();
+    }
+    assert_eq!(resolver.len(), 2);
+
+    // The stale region hierarchy is rebuilt first, then used by its children.
+    let resolution = resolver.resolve(&region).unwrap().unwrap();
+    assert_eq!(resolution.parent_id, 1);
+    assert_eq!(
+      resolution.hierarchies,
+      vec![object! { "country_id" => 1, "region_id" => 2 }]
+    );
+    resolver.update(2, &resolution);
+
+    let mut locality = square(4, "locality", 2.0, 2.5, object! {});
+    locality["properties"]["wof:belongsto"] = json::array![99];
+    let resolution = resolver.resolve(&locality).unwrap().unwrap();
+    resolution.apply(&mut locality).unwrap();
+    assert_eq!(locality["properties"]["wof:parent_id"], 2);
+    assert_eq!(
+      locality["properties"]["wof:hierarchy"],
+      json::array![object! { "country_id" => 1, "region_id" => 2, "locality_id" => 4 }]
+    );
+    assert_eq!(locality["properties"]["wof:belongsto"], json::array![1, 2]);
+
+    assert_eq!(resolver.resolve(&country).unwrap(), None);
+    let outside = square(5, "locality", 20.0, 21.0, object! {});
+    assert_eq!(resolver.resolve(&outside).unwrap(), None);
+  }
+}
@@ -0,0 +1,390 @@
+//! Check references between the documents of a repository or a database.
+use crate::placetypes::can_be_parent;
+use crate::repo::Walk;
+use crate::sqlite::{SQLite, SQLiteOpts};
+use crate::std::StringifyError;
+use crate::utils::JsonUtils;
+use crate::wof::WOFGeoJSON;
+use crate::{JsonObject, JsonValue, Predicate};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// A broken reference found in a document, `rule` is a short name of the failing check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntegrityError {
+  pub id: i64,
+  pub rule: &'static str,
+  pub message: String,
+}
+
+impl IntegrityError {
+  fn new(id: i64, rule: &'static str, message: String) -> Self {
+    IntegrityError { id, rule, message }
+  }
+
+  pub fn to_json(&self) -> JsonValue {
+    json::object! {
+      "id" => self.id,
+      "rule" => self.rule,
+      "message" => self.message.as_str(),
+    }
+  }
+}
+
+/// Properties of a document needed to check its references.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Record {
+  pub placetype: String,
+  pub parent_id: i64,
+  pub hierarchies: Vec<Vec<(String, i64)>>,
+  pub supersedes: Vec<i64>,
+  pub superseded_by: Vec<i64>,
+}
+
+/// Index of all the documents by id, alternate geometries are ignored.
+#[derive(Debug, Default)]
+pub struct IdIndex {
+  records: BTreeMap<i64, Record>,
+  unreadable: Vec<IntegrityError>,
+}
+
+impl IdIndex {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Index all documents of a directory, files that can't be read are reported by `check`.
+  pub fn add_directory<P: AsRef<Path>>(&mut self, directory: P) -> Result<(), String> {
+    for entry in Walk::new(directory, false, true) {
+      let entry = entry.stringify_err("Can't walk through the directory")?;
+      let path = entry.path();
+      let result = match crate::parse_file_to_json(path) {
+        Ok(json) => self.add(&json).map_err(|e| ("geojson", e)),
+        Err(e) => Err(("json", e)),
+      };
+      if let Err((rule, e)) = result {
+        self.unreadable.push(IntegrityError::new(
+          get_file_id(path),
+          rule,
+          format!("{}: {}", path.display(), e),
+        ));
+      }
+    }
+    Ok(())
+  }
+
+  /// Index all documents of a database, from the properties table when it exists or from the geojson table.
+  pub fn add_sqlite<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
+    let sqlite = SQLite::new(
+      path,
+      SQLiteOpts {
+        alt: false,
+        deprecated: true,
+        ..Default::default()
+      },
+    )?;
+    if sqlite.has_table("properties")? {
+      return sqlite.for_each_properties(|id, properties| self.add_properties(id, properties));
+    }
+    sqlite.for_each_geojson(&Predicate::Boolean(true), |json| self.add(json))
+  }
+
+  /// Add a document to the index, alternate geometries are skipped.
+  pub fn add(&mut self, json: &JsonValue) -> Result<(), String> {
+    let wof_obj = WOFGeoJSON::as_valid_wof_geojson(json)?;
+    if wof_obj.is_alt_geom() {
+      return Ok(());
+    }
+    let id = json["id"]
+      .as_i64()
+      .ok_or("Top level `id` is not a number")?;
+    self.add_properties(id, &json["properties"])
+  }
+
+  /// Add a document to the index from its properties only.
+  pub fn add_properties(&mut self, id: i64, properties: &JsonValue) -> Result<(), String> {
+    properties.assert_is_object()?;
+    let placetype = properties["placetype"]
+      .as_str()
+      .or(properties["wof:placetype"].as_str())
+      .unwrap_or_default()
+      .to_string();
+    let hierarchies = properties["wof:hierarchy"]
+      .members()
+      .filter_map(|hierarchy| hierarchy.as_object())
+      .map(get_hierarchy)
+      .collect();
+    self.records.insert(
+      id,
+      Record {
+        placetype,
+        parent_id: properties["wof:parent_id"].as_i64().unwrap_or(-1),
+        hierarchies,
+        supersedes: get_ids(&properties["wof:supersedes"]),
+        superseded_by: get_ids(&properties["wof:superseded_by"]),
+      },
+    );
+    Ok(())
+  }
+
+  pub fn get(&self, id: i64) -> Option<&Record> {
+    self.records.get(&id)
+  }
+
+  pub fn len(&self) -> usize {
+    self.records.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.records.is_empty()
+  }
+
+  /// Run all checks on the indexed documents, errors are sorted by id.
+  pub fn check(&self) -> Vec<IntegrityError> {
+    let mut errors: Vec<IntegrityError> = self.unreadable.clone();
+    for (id, record) in &self.records {
+      self.check_parent(*id, record, &mut errors);
+      self.check_ancestors(*id, record, &mut errors);
+      self.check_supersession(*id, record, &mut errors);
+    }
+    errors.sort_by_key(|error| error.id);
+    errors
+  }
+
+  fn check_parent(&self, id: i64, record: &Record, errors: &mut Vec<IntegrityError>) {
+    if record.parent_id <= 0 {
+      return;
+    }
+    let parent = match self.get(record.parent_id) {
+      Some(parent) => parent,
+      None => {
+        errors.push(IntegrityError::new(
+          id,
+          "parent",
+          format!("Parent {} does not exist", record.parent_id),
+        ));
+        return;
+      }
+    };
+    if !can_be_parent(&parent.placetype, &record.placetype) {
+      errors.push(IntegrityError::new(
+        id,
+        "placetype",
+        format!(
+          "A {} can't have the {} {} as parent",
+          record.placetype, parent.placetype, record.parent_id
+        ),
+      ));
+    }
+    if !record.hierarchies.is_empty()
+      && !parent.hierarchies.is_empty()
+      && !record.hierarchies.iter().any(|hierarchy| {
+        parent
+          .hierarchies
+          .iter()
+          .any(|parent_hierarchy| same_hierarchy(hierarchy, parent_hierarchy))
+      })
+    {
+      errors.push(IntegrityError::new(
+        id,
+        "hierarchy",
+        format!(
+          "No hierarchy agrees with the hierarchies of the parent {}",
+          record.parent_id
+        ),
+      ));
+    }
+  }
+
+  fn check_ancestors(&self, id: i64, record: &Record, errors: &mut Vec<IntegrityError>) {
+    let ancestors: BTreeSet<(i64, &str)> = record
+      .hierarchies
+      .iter()
+      .flatten()
+      .map(|(key, ancestor_id)| (*ancestor_id, key.as_str()))
+      .filter(|(ancestor_id, _)| *ancestor_id > 0 && *ancestor_id != id)
+      .collect();
+    for (ancestor_id, key) in ancestors {
+      if self.get(ancestor_id).is_none() {
+        errors.push(IntegrityError::new(
+          id,
+          "ancestor",
+          format!("Ancestor `{}` {} does not exist", key, ancestor_id),
+        ));
+      }
+    }
+  }
+
+  fn check_supersession(&self, id: i64, record: &Record, errors: &mut Vec<IntegrityError>) {
+    for (property, ids, reverse_property) in [
+      ("wof:supersedes", &record.supersedes, "wof:superseded_by"),
+      ("wof:superseded_by", &record.superseded_by, "wof:supersedes"),
+    ] {
+      for other_id in ids {
+        match self.get(*other_id) {
+          None => errors.push(IntegrityError::new(
+            id,
+            "supersession",
+            format!("`{}` target {} does not exist", property, other_id),
+          )),
+          Some(other) => {
+            let reverse_ids = if reverse_property == "wof:supersedes" {
+              &other.supersedes
+            } else {
+              &other.superseded_by
+            };
+            if !reverse_ids.contains(&id) {
+              errors.push(IntegrityError::new(
+                id,
+                "reciprocity",
+                format!(
+                  "`{}` contains {} but its `{}` does not contain {}",
+                  property, other_id, reverse_property, id
+                ),
+              ));
+            }
+          }
+        }
+      }
+    }
+  }
+}
+
+/// Id from the name of a document file, -1 when the name does not start with an id.
+fn get_file_id(path: &Path) -> i64 {
+  path
+    .file_name()
+    .and_then(|name| name.to_str())
+    .and_then(|name| name.split(['-', '.']).next())
+    .and_then(|id| id.parse().ok())
+    .unwrap_or(-1)
+}
+
+fn get_hierarchy(hierarchy: &JsonObject) -> Vec<(String, i64)> {
+  hierarchy
+    .iter()
+    .filter(|(key, _)| key.ends_with("_id"))
+    .filter_map(|(key, id)| id.as_i64().map(|id| (key.to_string(), id)))
+    .collect()
+}
+
+fn get_ids(ids: &JsonValue) -> Vec<i64> {
+  ids.members().filter_map(JsonValue::as_i64).collect()
+}
+
+/// Two hierarchies agree when all known ancestors they have in common are the same.
+fn same_hierarchy(hierarchy: &[(String, i64)], other: &[(String, i64)]) -> bool {
+  hierarchy.iter().all(|(key, id)| {
+    other
+      .iter()
+      .find(|(other_key, _)| other_key == key)
+      .is_none_or(|(_, other_id)| *id <= 0 || *other_id <= 0 || id == other_id)
+  })
+}
+
+#[cfg(test)]
+mod test_integrity {
+  use super::*;
+  use json::object;
+
+  fn document(id: i64, placetype: &str, parent_id: i64, hierarchy: JsonValue) -> JsonValue {
+    object! {
+      "id" => id,
+      "type" => "Feature",
+      "properties" => object!{
+        "wof:id" => id,
+        "wof:placetype" => placetype,
+        "wof:parent_id" => parent_id,
+        "wof:hierarchy" => vec![hierarchy],
+      },
+      "geometry" => object!{ "coordinates" => vec![0, 0], "type" => "Point" },
+      "bbox" => vec![0, 0, 0, 0],
+    }
+  }
+
+  fn rules(index: &IdIndex) -> Vec<(i64, &'static str)> {
+    index
+      .check()
+      .iter()
+      .map(|error| (error.id, error.rule))
+      .collect()
+  }
+
+  #[test]
+  fn valid_index() {
+    let mut index = IdIndex::new();
+    index
+      .add(&document(1, "country", -1, object! { "country_id" => 1 }))
+      .unwrap();
+    index
+      .add(&document(
+        2,
+        "locality",
+        1,
+        object! { "country_id" => 1, "locality_id" => 2 },
+      ))
+      .unwrap();
+    let mut alt = document(2, "country", 3, object! {});
+    alt["properties"]["src:alt_label"] = "quattroshapes".into();
+    index.add(&alt).unwrap();
+    assert_eq!(index.len(), 2);
+    assert_eq!(rules(&index), vec![]);
+  }
+
+  #[test]
+  fn broken_references() {
+    let mut index = IdIndex::new();
+    index
+      .add(&document(1, "country", -1, object! { "country_id" => 1 }))
+      .unwrap();
+    index
+      .add(&document(
+        2,
+        "locality",
+        1,
+        object! { "country_id" => 1, "region_id" => 4, "locality_id" => 2 },
+      ))
+      .unwrap();
+    index
+      .add(&document(
+        3,
+        "country",
+        2,
+        object! { "country_id" => 5, "locality_id" => 2 },
+      ))
+      .unwrap();
+    index
+      .add(&document(6, "locality", 7, object! { "locality_id" => 6 }))
+      .unwrap();
+    let mut superseded = document(8, "locality", 1, object! { "country_id" => 1 });
+    superseded["properties"]["wof:superseded_by"] = json::array![2, 9];
+    index.add(&superseded).unwrap();
+    assert_eq!(
+      rules(&index),
+      vec![
+        (2, "ancestor"),
+        (3, "placetype"),
+        (3, "hierarchy"),
+        (3, "ancestor"),
+        (6, "parent"),
+        (8, "reciprocity"),
+        (8, "supersession"),
+      ]
+    );
+  }
+
+  #[test]
+  fn unreadable_files() {
+    let directory = std::env::temp_dir().join(format!("wof-integrity-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let country = document(1, "country", -1, object! { "country_id" => 1 });
+    std::fs::write(directory.join("1.geojson"), country.dump()).unwrap();
+    std::fs::write(directory.join("2.geojson"), "{\"id\": 2,").unwrap();
+    std::fs::write(directory.join("3.geojson"), "{\"id\": 3}").unwrap();
+    let mut index = IdIndex::new();
+    index.add_directory(&directory).unwrap();
+    std::fs::remove_dir_all(&directory).unwrap();
+    assert_eq!(index.len(), 1);
+    assert_eq!(rules(&index), vec![(2, "json"), (3, "geojson")]);
+  }
+}
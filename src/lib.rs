@@ -31,11 +31,17 @@ mod wof;
 pub use self::wof::WOFGeoJSON;
 pub use json::object::Object as JsonObject;
 pub use json::JsonValue;
+pub mod create;
+pub mod diff;
 pub mod export;
 pub mod fix;
+pub mod hierarchy;
+pub mod integrity;
+pub mod lifecycle;
+pub mod pip;
+pub mod placetypes;
 pub mod postgres;
 pub mod repo;
 pub mod shapefile;
 pub mod types;
-#[macro_use]
-extern crate lazy_static;
+pub mod validate;
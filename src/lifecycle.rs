@@ -0,0 +1,141 @@
+//! Deprecate, cessate and supersede documents, both sides of a supersession are updated.
+use crate::JsonValue;
+
+/// Set `edtf:deprecated`, the document is no longer current.
+pub fn deprecate(json: &mut JsonValue, date: &str) -> Result<(), String> {
+  set_date(json, "edtf:deprecated", date)
+}
+
+/// Set `edtf:cessation`, the document is no longer current.
+pub fn cessate(json: &mut JsonValue, date: &str) -> Result<(), String> {
+  set_date(json, "edtf:cessation", date)
+}
+
+fn set_date(json: &mut JsonValue, key: &str, date: &str) -> Result<(), String> {
+  if !json["properties"].is_object() {
+    return Err("`properties` key must be an object".to_string());
+  }
+  json["properties"][key] = date.into();
+  json["properties"]["mz:is_current"] = 0.into();
+  Ok(())
+}
+
+/// The `old` document is superseded by the `new` one, ids are added once to `wof:superseded_by`
+/// and `wof:supersedes`.
+pub fn supersede(old: &mut JsonValue, new: &mut JsonValue) -> Result<(), String> {
+  let old_id = old["id"].as_i64().ok_or("Top level `id` is not a number")?;
+  let new_id = new["id"].as_i64().ok_or("Top level `id` is not a number")?;
+  if old_id == new_id {
+    return Err(format!("{} can't supersede itself", old_id));
+  }
+  add_id(old, "wof:superseded_by", new_id)?;
+  add_id(new, "wof:supersedes", old_id)?;
+  old["properties"]["mz:is_current"] = 0.into();
+  Ok(())
+}
+
+fn add_id(json: &mut JsonValue, key: &str, id: i64) -> Result<(), String> {
+  if !json["properties"].is_object() {
+    return Err("`properties` key must be an object".to_string());
+  }
+  let ids = &mut json["properties"][key];
+  if ids.is_null() {
+    *ids = JsonValue::new_array();
+  }
+  if !ids.is_array() {
+    return Err(format!("`{}` must be an array", key));
+  }
+  if !ids.members().any(|member| member.as_i64() == Some(id)) {
+    ids
+      .push(id)
+      .map_err(|e| format!("Can't update `{}`: {}", key, e))?;
+  }
+  Ok(())
+}
+
+/// Copy a document with a new id, its supersessions and end dates are reset and its hierarchies
+/// point to the new id.
+pub fn clone_successor(json: &JsonValue, id: i64) -> Result<JsonValue, String> {
+  let old_id = json["id"]
+    .as_i64()
+    .ok_or("Top level `id` is not a number")?;
+  let mut successor = json.clone();
+  if !successor["properties"].is_object() {
+    return Err("`properties` key must be an object".to_string());
+  }
+  successor["id"] = id.into();
+  let properties = &mut successor["properties"];
+  properties["wof:id"] = id.into();
+  properties["wof:supersedes"] = JsonValue::new_array();
+  properties["wof:superseded_by"] = JsonValue::new_array();
+  properties["edtf:cessation"] = "uuuu".into();
+  properties.remove("edtf:deprecated");
+  properties.remove("mz:is_current");
+  properties.remove("wof:lastmodified");
+  for hierarchy in properties["wof:hierarchy"].members_mut() {
+    let keys: Vec<String> = hierarchy
+      .entries()
+      .filter(|(_, value)| value.as_i64() == Some(old_id))
+      .map(|(key, _)| key.to_string())
+      .collect();
+    for key in keys {
+      hierarchy[key.as_str()] = id.into();
+    }
+  }
+  Ok(successor)
+}
+
+#[cfg(test)]
+mod test_lifecycle {
+  use super::*;
+  use json::object;
+
+  fn document(id: i64) -> JsonValue {
+    object! {
+      "id" => id,
+      "type" => "Feature",
+      "properties" => object!{
+        "wof:id" => id,
+        "wof:placetype" => "locality",
+        "mz:is_current" => 1,
+        "wof:hierarchy" => vec![object!{ "country_id" => 1, "locality_id" => id }],
+      },
+      "geometry" => object!{ "coordinates" => vec![0, 0], "type" => "Point" },
+      "bbox" => vec![0, 0, 0, 0],
+    }
+  }
+
+  #[test]
+  fn deprecate_and_cessate() {
+    let mut json = document(2);
+    deprecate(&mut json, "2020-01-01").unwrap();
+    cessate(&mut json, "2019-12-31").unwrap();
+    assert_eq!(json["properties"]["edtf:deprecated"], "2020-01-01");
+    assert_eq!(json["properties"]["edtf:cessation"], "2019-12-31");
+    assert_eq!(json["properties"]["mz:is_current"], 0);
+    let wof_obj = crate::wof::WOFGeoJSON::as_valid_wof_geojson(&json).unwrap();
+    assert!(wof_obj.is_deprecated());
+    assert!(wof_obj.is_ceased());
+    assert!(wof_obj.is_doc_deprecated());
+  }
+
+  #[test]
+  fn supersede_with_clone() {
+    let mut old = document(2);
+    let mut new = clone_successor(&old, 3).unwrap();
+    supersede(&mut old, &mut new).unwrap();
+    supersede(&mut old, &mut new).unwrap();
+    assert_eq!(old["properties"]["wof:superseded_by"], json::array![3]);
+    assert_eq!(old["properties"]["mz:is_current"], 0);
+    assert_eq!(new["id"], 3);
+    assert_eq!(new["properties"]["wof:id"], 3);
+    assert_eq!(new["properties"]["wof:supersedes"], json::array![2]);
+    assert_eq!(new["properties"]["wof:superseded_by"], json::array![]);
+    assert_eq!(new["properties"]["mz:is_current"], JsonValue::Null);
+    assert_eq!(
+      new["properties"]["wof:hierarchy"],
+      json::array![object! { "country_id" => 1, "locality_id" => 3 }]
+    );
+    assert!(supersede(&mut old.clone(), &mut old).is_err());
+  }
+}
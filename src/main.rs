@@ -1,9 +1,6 @@
 use crate::commands::Command;
 use clap::{Args, CommandFactory, Parser};
 
-#[macro_use]
-extern crate lazy_static;
-
 mod commands;
 pub mod expression;
 mod git;
@@ -13,14 +10,22 @@ mod ser;
 pub use self::ser::*;
 mod de;
 pub use self::de::*;
+mod create;
+mod diff;
 pub mod export;
 mod fix;
+mod hierarchy;
+mod integrity;
+mod lifecycle;
+mod pip;
+mod placetypes;
 mod postgres;
 mod shapefile;
 mod sqlite;
 mod std;
 pub mod types;
 pub mod utils;
+mod validate;
 mod wof;
 pub use self::wof::WOFGeoJSON;
 pub use json::object::Object as JsonObject;
@@ -0,0 +1,216 @@
+//! Point in polygon lookup, find which WOF documents contain a coordinate.
+use crate::placetypes::get_rank;
+use crate::types::MultiPolygon;
+use crate::utils::compute::polygon_contains_point;
+use crate::wof::WOFGeoJSON;
+use crate::JsonValue;
+use std::collections::HashMap;
+
+/// Size in degrees of the cells of the spatial index.
+const CELL_SIZE: f64 = 1.0;
+/// Features covering more cells are always candidates, it keeps the index small.
+const MAX_CELLS: i64 = 1024;
+
+/// A document containing the point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipResult {
+  pub id: i64,
+  pub placetype: String,
+  pub name: String,
+}
+
+impl PipResult {
+  pub fn to_json(&self) -> JsonValue {
+    json::object! {
+      "id" => self.id,
+      "placetype" => self.placetype.as_str(),
+      "name" => self.name.as_str(),
+    }
+  }
+}
+
+#[derive(Debug)]
+struct PipFeature {
+  result: PipResult,
+  bbox: [f64; 4],
+  polygons: MultiPolygon,
+}
+
+/// In memory index of polygons, the candidates of a point are selected with a grid.
+#[derive(Debug, Default)]
+pub struct PipIndex {
+  features: Vec<PipFeature>,
+  cells: HashMap<(i64, i64), Vec<usize>>,
+  large: Vec<usize>,
+}
+
+impl PipIndex {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Add a document to the index, returns false when it's an alternate geometry or not a polygon.
+  pub fn add(&mut self, json: &JsonValue) -> Result<bool, String> {
+    let wof_obj = WOFGeoJSON::as_valid_wof_geojson(json)?;
+    if wof_obj.is_alt_geom() {
+      return Ok(false);
+    }
+    let polygons = match wof_obj.get_polygons() {
+      Some(polygons) if !polygons.is_empty() => polygons,
+      _ => return Ok(false),
+    };
+    let bbox = match get_bbox(&polygons) {
+      Some(bbox) => bbox,
+      None => return Ok(false),
+    };
+    let index = self.features.len();
+    let (min_x, min_y) = cell(bbox[0], bbox[1]);
+    let (max_x, max_y) = cell(bbox[2], bbox[3]);
+    if (max_x - min_x + 1) * (max_y - min_y + 1) > MAX_CELLS {
+      self.large.push(index);
+    } else {
+      for x in min_x..=max_x {
+        for y in min_y..=max_y {
+          self.cells.entry((x, y)).or_default().push(index);
+        }
+      }
+    }
+    self.features.push(PipFeature {
+      result: PipResult {
+        id: json["id"].as_i64().unwrap_or(wof_obj.id as i64),
+        placetype: wof_obj.get_placetype(),
+        name: wof_obj.get_name(),
+      },
+      bbox,
+      polygons,
+    });
+    Ok(true)
+  }
+
+  pub fn len(&self) -> usize {
+    self.features.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.features.is_empty()
+  }
+
+  /// Returns the documents containing the point, ordered by placetype from the largest.
+  pub fn lookup(&self, lon: f64, lat: f64) -> Vec<PipResult> {
+    let point = [lon, lat];
+    let candidates = self
+      .cells
+      .get(&cell(lon, lat))
+      .into_iter()
+      .flatten()
+      .chain(self.large.iter());
+    let mut results: Vec<PipResult> = candidates
+      .map(|index| &self.features[*index])
+      .filter(|feature| {
+        let bbox = feature.bbox;
+        bbox[0] <= lon && lon <= bbox[2] && bbox[1] <= lat && lat <= bbox[3]
+      })
+      .filter(|feature| {
+        feature
+          .polygons
+          .iter()
+          .any(|polygon| polygon_contains_point(polygon, &point))
+      })
+      .map(|feature| feature.result.clone())
+      .collect();
+    sort_results(&mut results);
+    results
+  }
+}
+
+/// Sort results by placetype rank, unknown placetypes are last.
+fn sort_results(results: &mut [PipResult]) {
+  results.sort_by_key(|result| (get_rank(&result.placetype).unwrap_or(u8::MAX), result.id));
+}
+
+fn cell(lon: f64, lat: f64) -> (i64, i64) {
+  (
+    (lon / CELL_SIZE).floor() as i64,
+    (lat / CELL_SIZE).floor() as i64,
+  )
+}
+
+/// Bounding box of the outer rings.
+fn get_bbox(polygons: &MultiPolygon) -> Option<[f64; 4]> {
+  let mut points = polygons
+    .iter()
+    .filter_map(|polygon| polygon.first())
+    .flatten()
+    .filter(|point| point.len() >= 2);
+  let first = points.next()?;
+  Some(
+    points.fold([first[0], first[1], first[0], first[1]], |bbox, point| {
+      [
+        bbox[0].min(point[0]),
+        bbox[1].min(point[1]),
+        bbox[2].max(point[0]),
+        bbox[3].max(point[1]),
+      ]
+    }),
+  )
+}
+
+/// Parse a `lon,lat` coordinate.
+pub fn parse_coordinate(coordinate: &str) -> Result<(f64, f64), String> {
+  let parts: Vec<&str> = coordinate.split(',').map(str::trim).collect();
+  if parts.len() != 2 {
+    return Err(format!("`{}` is not a `lon,lat` coordinate", coordinate));
+  }
+  let lon = parts[0]
+    .parse::<f64>()
+    .map_err(|_| format!("`{}` is not a longitude", parts[0]))?;
+  let lat = parts[1]
+    .parse::<f64>()
+    .map_err(|_| format!("`{}` is not a latitude", parts[1]))?;
+  Ok((lon, lat))
+}
+
+#[cfg(test)]
+mod test_pip {
+  use super::*;
+  use json::object;
+
+  fn square(id: i64, placetype: &str, min: f64, max: f64) -> JsonValue {
+    object! {
+      "id" => id,
+      "type" => "Feature",
+      "properties" => object!{ "wof:id" => id, "wof:name" => format!("Place {}", id), "wof:placetype" => placetype },
+      "geometry" => object!{
+        "coordinates" => vec![vec![vec![min, min], vec![max, min], vec![max, max], vec![min, max], vec![min, min]]],
+        "type" => "Polygon"
+      },
+      "bbox" => vec![min, min, max, max],
+    }
+  }
+
+  #[test]
+  fn lookup() {
+    let mut index = PipIndex::new();
+    assert!(index.add(&square(3, "locality", 1.0, 2.0)).unwrap());
+    assert!(index.add(&square(1, "country", -50.0, 50.0)).unwrap());
+    assert!(index.add(&square(2, "region", 0.5, 10.0)).unwrap());
+    let mut point = square(4, "venue", 1.5, 1.5);
+    point["geometry"] = object! { "coordinates" => vec![1.5, 1.5], "type" => "Point" };
+    assert!(!index.add(&point).unwrap());
+    assert_eq!(index.len(), 3);
+
+    let ids = |results: Vec<PipResult>| -> Vec<i64> { results.iter().map(|r| r.id).collect() };
+    assert_eq!(ids(index.lookup(1.5, 1.5)), vec![1, 2, 3]);
+    assert_eq!(ids(index.lookup(5.0, 5.0)), vec![1, 2]);
+    assert_eq!(ids(index.lookup(-10.0, 20.0)), vec![1]);
+    assert_eq!(ids(index.lookup(60.0, 0.0)), Vec::<i64>::new());
+    assert_eq!(index.lookup(1.5, 1.5)[2].name, "Place 3");
+  }
+
+  #[test]
+  fn coordinate() {
+    assert_eq!(parse_coordinate("55.66, -21.08"), Ok((55.66, -21.08)));
+    assert!(parse_coordinate("55.66").is_err());
+    assert!(parse_coordinate("lon,lat").is_err());
+  }
+}
@@ -0,0 +1,68 @@
+//! Order of the WOF placetypes, from the planet to the venues.
+
+/// Known placetypes with their rank, a parent must always have a lower rank than its children.
+/// Placetypes sharing a rank can't be the parent of each other.
+pub const PLACETYPES: [(&str, u8); 26] = [
+  ("planet", 0),
+  ("continent", 1),
+  ("ocean", 1),
+  ("empire", 2),
+  ("country", 3),
+  ("dependency", 4),
+  ("disputed", 4),
+  ("marinearea", 5),
+  ("macroregion", 5),
+  ("region", 6),
+  ("macrocounty", 7),
+  ("county", 8),
+  ("localadmin", 9),
+  ("locality", 10),
+  ("borough", 11),
+  ("postalcode", 11),
+  ("macrohood", 12),
+  ("neighbourhood", 13),
+  ("microhood", 14),
+  ("campus", 15),
+  ("building", 16),
+  ("wing", 17),
+  ("address", 17),
+  ("intersection", 17),
+  ("concourse", 17),
+  ("venue", 18),
+];
+
+/// Returns the rank of a placetype, `None` when the placetype is unknown.
+/// ```rust
+/// use wof::placetypes::get_rank;
+/// assert!(get_rank("country") < get_rank("locality"));
+/// assert_eq!(get_rank("unknown"), None);
+/// ```
+pub fn get_rank(placetype: &str) -> Option<u8> {
+  PLACETYPES
+    .iter()
+    .find(|(name, _)| *name == placetype)
+    .map(|(_, rank)| *rank)
+}
+
+/// Returns true when `parent` can be the parent of `child`, unknown placetypes are always accepted.
+pub fn can_be_parent(parent: &str, child: &str) -> bool {
+  match (get_rank(parent), get_rank(child)) {
+    (Some(parent), Some(child)) => parent < child,
+    _ => true,
+  }
+}
+
+#[cfg(test)]
+mod test_placetypes {
+  use super::*;
+
+  #[test]
+  fn order() {
+    assert!(can_be_parent("country", "region"));
+    assert!(can_be_parent("country", "dependency"));
+    assert!(can_be_parent("locality", "neighbourhood"));
+    assert!(!can_be_parent("locality", "country"));
+    assert!(!can_be_parent("dependency", "disputed"));
+    assert!(can_be_parent("custom", "country"));
+  }
+}
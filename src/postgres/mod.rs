@@ -44,6 +44,8 @@ impl Postgres {
   }
 
   /// Add a file to the database, the file must be a WOF GeoJSON.
+  // Library API, builds add documents through `Destination`.
+  #[allow(dead_code)]
   pub fn add_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
     let json = crate::parse_file_to_json(path.as_ref().to_path_buf())?;
     let geojson = WOFGeoJSON::as_valid_wof_geojson(&json)?;
@@ -51,6 +53,8 @@ impl Postgres {
   }
 
   /// Add the string content to the database, it must be a WOF GeoJSON.
+  // Library API, builds add documents through `Destination`.
+  #[allow(dead_code)]
   pub fn add_string(&mut self, buf: String) -> Result<(), String> {
     let json = crate::parse_string_to_json(&buf)?;
     let geojson = WOFGeoJSON::as_valid_wof_geojson(&json)?;
@@ -1,12 +1,15 @@
 //! Module to create and add documents to WOF SQLites databases.
 use crate::std::StringifyError;
+use crate::utils::GeoCompute;
 use crate::wof::WOFGeoJSON;
 use crate::{Evaluate, Predicate};
 use json::JsonValue;
-use rusqlite::{params, Connection, Error as SQLiteError};
+use rusqlite::{params, params_from_iter, Connection, Error as SQLiteError};
 use std::io::Write;
 use std::path::Path;
+mod pushdown;
 mod statements;
+pub use pushdown::SQLFilter;
 
 /// SQLite structure, own a connection to the database with options.
 #[derive(Debug)]
@@ -34,6 +37,14 @@ pub struct SQLiteOpts {
   pub concordances: bool,
   /// If true, will add alternative geometries in geojson table.
   pub alt: bool,
+  /// If true, will add names in the full text search table.
+  pub search: bool,
+  /// If true, will add the bbox of each polygon in the rtree table.
+  pub rtree: bool,
+  /// If true, will add the properties of documents, without their geometry, in properties table.
+  pub properties: bool,
+  /// If true, will add the geometry of documents in geometries table.
+  pub geometries: bool,
 }
 
 impl SQLite {
@@ -87,6 +98,38 @@ impl SQLite {
       .conn
       .execute_batch(statements::INDEXES_CONCORDANCES)
       .stringify_err("concordances indexes")?;
+    if self.opts.search {
+      self
+        .conn
+        .execute_batch(statements::TABLE_SEARCH)
+        .stringify_err("search table")?;
+    }
+    if self.opts.rtree {
+      self
+        .conn
+        .execute_batch(statements::TABLE_RTREE)
+        .stringify_err("rtree table")?;
+    }
+    if self.opts.properties {
+      self
+        .conn
+        .execute_batch(statements::TABLE_PROPERTIES)
+        .stringify_err("properties table")?;
+      self
+        .conn
+        .execute_batch(statements::INDEXES_PROPERTIES)
+        .stringify_err("properties indexes")?;
+    }
+    if self.opts.geometries {
+      self
+        .conn
+        .execute_batch(statements::TABLE_GEOMETRIES)
+        .stringify_err("geometries table")?;
+      self
+        .conn
+        .execute_batch(statements::INDEXES_GEOMETRIES)
+        .stringify_err("geometries indexes")?;
+    }
     self
       .conn
       .execute_batch(statements::PRAGMA)
@@ -94,6 +137,52 @@ impl SQLite {
     Ok(())
   }
 
+  /// Use a WAL journal, readers are not blocked by updates and an interrupted transaction is
+  /// rolled back. Meant for in-place updates, builds are faster without a journal.
+  pub fn set_safe_mode(&self) -> Result<(), String> {
+    self
+      .conn
+      .execute_batch(statements::PRAGMA_SAFE)
+      .stringify_err("safe mode pragma statements")
+  }
+
+  /// Write the pages of the WAL journal back to the database and truncate it.
+  pub fn checkpoint(&self) -> Result<(), String> {
+    self
+      .conn
+      .execute_batch(statements::CHECKPOINT)
+      .stringify_err("checkpoint")
+  }
+
+  /// Copy the database to a new file, with the content of its WAL journal.
+  pub fn copy_to<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+    let path = path
+      .as_ref()
+      .to_str()
+      .ok_or("The path of the copy is not valid UTF-8")?;
+    self
+      .conn
+      .execute(statements::VACUUM_INTO, params![path])
+      .stringify_err("copy database")?;
+    Ok(())
+  }
+
+  /// Start a transaction, documents added before `commit` are written at once.
+  pub fn begin(&self) -> Result<(), String> {
+    self
+      .conn
+      .execute_batch(statements::BEGIN_TRANSACTION)
+      .stringify_err("begin transaction")
+  }
+
+  /// Commit the transaction started by `begin`.
+  pub fn commit(&self) -> Result<(), String> {
+    self
+      .conn
+      .execute_batch(statements::COMMIT_TRANSACTION)
+      .stringify_err("commit transaction")
+  }
+
   /// Add a file to the database, the file must be a WOF GeoJSON.
   pub fn add_file<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
     let json = crate::parse_file_to_json(path.as_ref().to_path_buf())?;
@@ -102,6 +191,8 @@ impl SQLite {
   }
 
   /// Add the string content to the database, it must be a WOF GeoJSON.
+  // Library API, builds add documents through `Destination`.
+  #[allow(dead_code)]
   pub fn add_string(&self, buf: String) -> Result<(), String> {
     let json = crate::parse_string_to_json(&buf)?;
     let geojson = WOFGeoJSON::as_valid_wof_geojson(&json)?;
@@ -110,6 +201,7 @@ impl SQLite {
 
   /// Add a WOFGeoJSON document to the database.
   /// The `SQLiteOpts` is used here and it will define in which table the document should be added.
+  /// Alternate geometries are only added to the geojson table.
   pub fn add(&self, document: WOFGeoJSON) -> Result<(), String> {
     if !self.opts.deprecated && document.is_doc_deprecated() {
       return Ok(());
@@ -119,6 +211,27 @@ impl SQLite {
         .add_to_geojson(&document)
         .stringify_err("add document to geojson table")?;
     }
+    if self.opts.alt || !document.is_alt_geom() {
+      if self.opts.properties {
+        self
+          .add_to_properties(&document)
+          .stringify_err("add document to properties table")?;
+      }
+      if self.opts.geometries {
+        self
+          .add_to_geometries(&document)
+          .stringify_err("add document to geometries table")?;
+      }
+      if self.opts.rtree {
+        self
+          .add_to_rtree(&document)
+          .stringify_err("add document to rtree table")?;
+      }
+    }
+    // Other tables describe the main document, an alternate geometry would overwrite its rows.
+    if document.is_alt_geom() {
+      return Ok(());
+    }
     if self.opts.spr {
       self
         .add_to_spr(&document)
@@ -129,6 +242,11 @@ impl SQLite {
         .add_to_names(&document)
         .stringify_err("add document to names table")?;
     }
+    if self.opts.search {
+      self
+        .add_to_search(&document)
+        .stringify_err("add document to search table")?;
+    }
     if self.opts.ancestors {
       self
         .add_to_ancestors(&document)
@@ -199,6 +317,9 @@ impl SQLite {
   }
 
   fn add_to_names(&self, doc: &WOFGeoJSON) -> Result<(), SQLiteError> {
+    self
+      .conn
+      .execute(statements::DELETE_NAMES, params![doc.id])?;
     for name in doc.get_names() {
       self.conn.execute(
         statements::INSERT_NAMES,
@@ -221,7 +342,88 @@ impl SQLite {
     Ok(())
   }
 
+  fn add_to_search(&self, doc: &WOFGeoJSON) -> Result<(), SQLiteError> {
+    let mut names: Vec<&str> = vec![];
+    for name in doc.get_names() {
+      if !names.contains(&name.value) {
+        names.push(name.value);
+      }
+    }
+    self.conn.execute(
+      statements::INSERT_SEARCH,
+      params![
+        doc.id,
+        doc.get_placetype(),
+        doc.get_country(),
+        doc.get_name(),
+        names.join(" ")
+      ],
+    )?;
+    Ok(())
+  }
+
+  fn add_to_properties(&self, doc: &WOFGeoJSON) -> Result<(), SQLiteError> {
+    self.conn.execute(
+      statements::INSERT_PROPERTIES,
+      params![
+        doc.id,
+        doc.properties.dump(),
+        doc.is_alt_geom(),
+        doc.get_alt_label(),
+        doc.get_last_modified()
+      ],
+    )?;
+    Ok(())
+  }
+
+  fn add_to_geometries(&self, doc: &WOFGeoJSON) -> Result<(), SQLiteError> {
+    self.conn.execute(
+      statements::INSERT_GEOMETRIES,
+      params![
+        doc.id,
+        doc.geometry["type"].as_str(),
+        doc.is_alt_geom(),
+        doc.get_alt_label(),
+        doc.geometry.dump(),
+        doc.get_last_modified()
+      ],
+    )?;
+    Ok(())
+  }
+
+  fn add_to_rtree(&self, doc: &WOFGeoJSON) -> Result<(), SQLiteError> {
+    let alt_label = doc.get_alt_label();
+    // Polygons of the main document and of each alternate geometry are replaced separately.
+    self
+      .conn
+      .execute(statements::DELETE_RTREE, params![doc.id, alt_label])?;
+    for polygon in doc.get_polygons().unwrap_or_default() {
+      let bbox = match polygon.first() {
+        Some(exterior) if !exterior.is_empty() => exterior.compute_bbox(),
+        _ => continue,
+      };
+      self.conn.execute(
+        statements::INSERT_RTREE,
+        params![
+          bbox[0],
+          bbox[2],
+          bbox[1],
+          bbox[3],
+          doc.id,
+          doc.is_alt_geom(),
+          alt_label,
+          JsonValue::from(polygon).dump(),
+          doc.get_last_modified()
+        ],
+      )?;
+    }
+    Ok(())
+  }
+
   fn add_to_ancestors(&self, doc: &WOFGeoJSON) -> Result<(), SQLiteError> {
+    self
+      .conn
+      .execute(statements::DELETE_ANCESTORS, params![doc.id])?;
     for (ancestor_id, ancestor_placetype) in doc.get_ancestors() {
       self.conn.execute(
         statements::INSERT_ANCESTORS,
@@ -237,6 +439,9 @@ impl SQLite {
   }
 
   fn add_to_concordances(&self, doc: &WOFGeoJSON) -> Result<(), SQLiteError> {
+    self
+      .conn
+      .execute(statements::DELETE_CONCORDANCES, params![doc.id])?;
     for (concordance_id, concordance_source) in doc.get_concordances() {
       self.conn.execute(
         statements::INSERT_CONCORDANCES,
@@ -251,7 +456,40 @@ impl SQLite {
     Ok(())
   }
 
-  pub fn write_all_ids<W: Write>(&self, mut writer: &mut W) -> Result<(), String> {
+  /// Delete all rows of a document, alternate geometries included. The optional tables are
+  /// cleared when they are enabled in the options, like `create_tables` creates them.
+  pub fn delete(&self, id: i64) -> Result<(), String> {
+    for (enabled, table, statement) in [
+      (true, "geojson", statements::DELETE_GEOJSON),
+      (true, "spr", statements::DELETE_SPR),
+      (true, "names", statements::DELETE_NAMES),
+      (true, "ancestors", statements::DELETE_ANCESTORS),
+      (true, "concordances", statements::DELETE_CONCORDANCES),
+      (self.opts.search, "search", statements::DELETE_SEARCH),
+      (self.opts.rtree, "rtree", statements::DELETE_RTREE_BY_ID),
+      (
+        self.opts.properties,
+        "properties",
+        statements::DELETE_PROPERTIES,
+      ),
+      (
+        self.opts.geometries,
+        "geometries",
+        statements::DELETE_GEOMETRIES,
+      ),
+    ] {
+      if enabled {
+        self
+          .conn
+          .execute(statement, params![id])
+          .stringify_err(&format!("Can't delete {} from table {}", id, table))?;
+      }
+    }
+    Ok(())
+  }
+
+  /// Call `f` with each id of the geojson table, alternate and deprecated documents follow the options.
+  pub fn for_each_id<F: FnMut(i64) -> Result<(), String>>(&self, mut f: F) -> Result<(), String> {
     let sql = if !self.opts.alt && !self.opts.deprecated {
       statements::SELECT_ALL_IDS_WITHOUT_ALT_AND_DEPRECATED
     } else if !self.opts.alt {
@@ -274,32 +512,54 @@ impl SQLite {
       .stringify_err("Can't get rows of table geojson")?;
 
     for id in rows {
-      writeln!(&mut writer, "{}", id.unwrap()).stringify_err("Can't write to output")?;
+      f(id.unwrap())?;
     }
     Ok(())
   }
 
-  pub fn write_all_geojsons<W: Write>(
+  pub fn write_all_ids<W: Write>(&self, mut writer: &mut W) -> Result<(), String> {
+    self.for_each_id(|id| writeln!(&mut writer, "{}", id).stringify_err("Can't write to output"))
+  }
+
+  /// Build the query used by [`SQLite::write_all_geojsons`], the part of the predicate that can't
+  /// be compiled to SQL is in [`SQLFilter::remainder`].
+  pub fn geojsons_query(&self, predicate: &Predicate) -> Result<(String, SQLFilter), String> {
+    let filter = pushdown::compile(predicate, self.has_table("spr")?);
+    let mut conditions: Vec<&str> = vec![];
+    if !self.opts.alt {
+      conditions.push("geojson.is_alt = false");
+    }
+    if !self.opts.deprecated {
+      conditions.push("spr.is_deprecated = false");
+    }
+    conditions.extend(filter.conditions.iter().map(String::as_str));
+    let select = if !self.opts.deprecated || filter.uses_spr {
+      statements::SELECT_GEOJSONS_WITH_SPR
+    } else {
+      statements::SELECT_GEOJSONS
+    };
+    let sql = if conditions.is_empty() {
+      format!("{};", select)
+    } else {
+      format!("{} WHERE {};", select, conditions.join(" AND "))
+    };
+    Ok((sql, filter))
+  }
+
+  /// Call `f` with each geojson of the database matching the predicate.
+  pub fn for_each_geojson<F: FnMut(&JsonValue) -> Result<(), String>>(
     &self,
-    mut writer: &mut W,
     predicate: &Predicate,
+    mut f: F,
   ) -> Result<(), String> {
-    let sql = if !self.opts.alt && !self.opts.deprecated {
-      statements::SELECT_ALL_GEOJSONS_WITHOUT_ALT_AND_DEPRECATED
-    } else if !self.opts.alt {
-      statements::SELECT_ALL_GEOJSONS_WITHOUT_ALT
-    } else if !self.opts.deprecated {
-      statements::SELECT_ALL_GEOJSONS_WITHOUT_DEPRECATED
-    } else {
-      statements::SELECT_ALL_GEOJSONS
-    };
+    let (sql, filter) = self.geojsons_query(predicate)?;
     let mut stmt = self
       .conn
-      .prepare(sql)
+      .prepare(&sql)
       .stringify_err("Can't get table geojson")?;
 
     let rows = stmt
-      .query_map([], |row| {
+      .query_map(params_from_iter(filter.params.iter()), |row| {
         let res: Vec<u8> = row.get(0).unwrap();
         Ok(res)
       })
@@ -307,14 +567,190 @@ impl SQLite {
     for body in rows {
       let body = std::str::from_utf8(&body.unwrap()).unwrap().to_string();
       let json = crate::parse_string_to_json(&body).stringify_err("Can't parse geojson body")?;
-      if let Predicate::Boolean(true) = json.eval(&predicate)? {
-        crate::ser::json_to_writer(&json, &mut writer).stringify_err("Can't write to output")?;
-        writeln!(&mut writer, "").stringify_err("Can't write to output")?;
+      if let Predicate::Boolean(true) = json.eval(&filter.remainder)? {
+        f(&json)?;
+      }
+    }
+    Ok(())
+  }
+
+  pub fn write_all_geojsons<W: Write>(
+    &self,
+    mut writer: &mut W,
+    predicate: &Predicate,
+  ) -> Result<(), String> {
+    self.for_each_geojson(predicate, |json| {
+      crate::ser::json_to_writer(json, &mut writer).stringify_err("Can't write to output")?;
+      writeln!(&mut writer).stringify_err("Can't write to output")
+    })
+  }
+
+  /// Call `f` with the id and the properties of each document of the properties table, without
+  /// decoding geometries. Alternate geometries are skipped.
+  pub fn for_each_properties<F: FnMut(i64, &JsonValue) -> Result<(), String>>(
+    &self,
+    mut f: F,
+  ) -> Result<(), String> {
+    if !self.has_table("properties")? {
+      return Err(
+        "The properties table is missing, build the database with `--properties`".to_string(),
+      );
+    }
+    let mut stmt = self
+      .conn
+      .prepare(statements::SELECT_ALL_PROPERTIES_WITHOUT_ALT)
+      .stringify_err("Can't get table properties")?;
+    let mut rows = stmt
+      .query([])
+      .stringify_err("Can't get rows of table properties")?;
+    while let Some(row) = rows
+      .next()
+      .stringify_err("Can't read rows of table properties")?
+    {
+      let id: i64 = row.get(0).stringify_err("Can't read properties id")?;
+      let body: String = row.get(1).stringify_err("Can't read properties body")?;
+      let properties =
+        crate::parse_string_to_json(&body).stringify_err("Can't parse properties body")?;
+      f(id, &properties)?;
+    }
+    Ok(())
+  }
+
+  /// Search documents by name in the search table, the best matches come first.
+  /// Each word of the query matches the words starting with it, diacritics are ignored.
+  pub fn search(
+    &self,
+    query: &str,
+    placetype: Option<&str>,
+    country: Option<&str>,
+    limit: usize,
+  ) -> Result<Vec<JsonValue>, String> {
+    if !self.has_table("search")? {
+      return Err("The search table is missing, build the database with `--search`".to_string());
+    }
+    let query = to_fts_query(query);
+    if query.is_empty() {
+      return Ok(vec![]);
+    }
+    let mut stmt = self
+      .conn
+      .prepare(statements::SELECT_SEARCH)
+      .stringify_err("Can't get table search")?;
+    let rows = stmt
+      .query_map(params![query, placetype, country, limit as i64], |row| {
+        Ok(json::object! {
+          "id" => row.get::<_, i64>(0)?,
+          "parent_id" => row.get::<_, Option<i64>>(1)?,
+          "name" => row.get::<_, Option<String>>(2)?,
+          "placetype" => row.get::<_, Option<String>>(3)?,
+          "country" => row.get::<_, Option<String>>(4)?,
+          "repo" => row.get::<_, Option<String>>(5)?,
+          "latitude" => row.get::<_, Option<f64>>(6)?,
+          "longitude" => row.get::<_, Option<f64>>(7)?,
+          "is_current" => row.get::<_, Option<i64>>(8)?,
+          "is_deprecated" => row.get::<_, Option<i64>>(9)?,
+          "rank" => row.get::<_, f64>(10)?,
+        })
+      })
+      .stringify_err("Can't search in table search")?;
+    rows
+      .collect::<Result<Vec<JsonValue>, SQLiteError>>()
+      .stringify_err("Can't read rows of table search")
+  }
+
+  /// Returns the documents whose bbox contains the point, from the rtree table when the database has one
+  /// or from the spr table.
+  pub fn get_geojsons_by_point(&self, lon: f64, lat: f64) -> Result<Vec<JsonValue>, String> {
+    if self.has_table("rtree")? {
+      let mut geojsons = vec![];
+      for id in self.ids_intersecting_bbox(lon, lat, lon, lat)? {
+        let json = match self.get_geojson_by_id(id)? {
+          Some(json) => json,
+          None => continue,
+        };
+        if self.opts.deprecated || !WOFGeoJSON::as_valid_wof_geojson(&json)?.is_doc_deprecated() {
+          geojsons.push(json);
+        }
       }
+      return Ok(geojsons);
+    }
+    let sql = if self.opts.deprecated {
+      format!("{};", statements::SELECT_GEOJSONS_BY_POINT)
+    } else {
+      format!(
+        "{} AND spr.is_deprecated = 0 AND spr.is_superseded = 0 AND spr.is_current != 0;",
+        statements::SELECT_GEOJSONS_BY_POINT
+      )
+    };
+    let mut stmt = self
+      .conn
+      .prepare(&sql)
+      .stringify_err("Can't get table spr")?;
+    let rows = stmt
+      .query_map(params![lon, lat], |row| row.get::<_, Vec<u8>>(0))
+      .stringify_err("Can't get rows of table spr")?;
+    let mut geojsons = vec![];
+    for body in rows {
+      let body = body.stringify_err("Can't read geojson body")?;
+      let body = String::from_utf8(body).stringify_err("Can't read geojson body")?;
+      geojsons.push(crate::parse_string_to_json(&body).stringify_err("Can't parse geojson body")?);
     }
+    Ok(geojsons)
+  }
+
+  /// Returns the ids of the documents with a polygon intersecting the bbox, sorted and without duplicates.
+  /// Alternate geometries follow the options.
+  pub fn ids_intersecting_bbox(
+    &self,
+    min_lon: f64,
+    min_lat: f64,
+    max_lon: f64,
+    max_lat: f64,
+  ) -> Result<Vec<i64>, String> {
+    if !self.has_table("rtree")? {
+      return Err("The rtree table is missing, build the database with `--rtree`".to_string());
+    }
+    let mut stmt = self
+      .conn
+      .prepare(statements::SELECT_RTREE_IDS_BY_BBOX)
+      .stringify_err("Can't get table rtree")?;
+    let rows = stmt
+      .query_map(
+        params![min_lon, min_lat, max_lon, max_lat, self.opts.alt],
+        |row| row.get::<_, i64>(0),
+      )
+      .stringify_err("Can't get rows of table rtree")?;
+    rows
+      .collect::<Result<Vec<i64>, SQLiteError>>()
+      .stringify_err("Can't read rows of table rtree")
+  }
+
+  /// Fill the optional tables found in the database and only them, whatever the options. Documents
+  /// rewritten in an existing database then leave no stale rows behind.
+  pub fn use_existing_tables(&mut self) -> Result<(), String> {
+    self.opts.search = self.has_table("search")?;
+    self.opts.rtree = self.has_table("rtree")?;
+    self.opts.properties = self.has_table("properties")?;
+    self.opts.geometries = self.has_table("geometries")?;
     Ok(())
   }
 
+  /// Options of the database, see [`SQLite::use_existing_tables`].
+  pub fn opts(&self) -> &SQLiteOpts {
+    &self.opts
+  }
+
+  /// Returns true when the table exists, optional tables are only created with their option.
+  pub fn has_table(&self, table: &str) -> Result<bool, String> {
+    self
+      .conn
+      .query_row(statements::SELECT_TABLE_EXISTS, params![table], |row| {
+        row.get::<_, i64>(0)
+      })
+      .map(|count| count > 0)
+      .stringify_err("Can't read database schema")
+  }
+
   pub fn get_geojson_by_id(&self, id: i64) -> Result<Option<JsonValue>, String> {
     let mut stmt = self
       .conn
@@ -357,10 +793,24 @@ impl Default for SQLiteOpts {
       ancestors: true,
       concordances: true,
       alt: true,
+      search: false,
+      rtree: false,
+      properties: false,
+      geometries: false,
     }
   }
 }
 
+/// Each word of the query is quoted to escape the FTS5 syntax and used as a prefix.
+fn to_fts_query(query: &str) -> String {
+  query
+    .split(|c: char| !c.is_alphanumeric())
+    .filter(|word| !word.is_empty())
+    .map(|word| format!("\"{}\"*", word))
+    .collect::<Vec<String>>()
+    .join(" ")
+}
+
 fn bool_to_i32(b: bool) -> i32 {
   if b {
     1
@@ -368,3 +818,462 @@ fn bool_to_i32(b: bool) -> i32 {
     0
   }
 }
+
+#[cfg(test)]
+mod test_sqlite {
+  use super::*;
+  use json::object;
+  use std::convert::TryFrom;
+
+  fn feature(id: i32, placetype: &str, is_current: i32, alt: bool) -> JsonValue {
+    let mut json = object! {
+      "type" => "Feature",
+      "properties" => object!{
+        "wof:id" => id,
+        "wof:name" => format!("Place {}", id),
+        "wof:placetype" => placetype,
+        "wof:hierarchy" => vec![object!{ "country_id" => 85633147 }],
+        "wof:population" => id * 10,
+        "mz:is_current" => is_current,
+      },
+      "geometry" => object!{
+        "coordinates" => vec![8.7, 41.9],
+        "type" => "Point"
+      },
+      "bbox" => vec![8.7, 41.9, 8.7, 41.9],
+      "id" => id,
+    };
+    if alt {
+      json["properties"]["src:alt_label"] = "quattroshapes".into();
+      json["properties"]["src:geom"] = "quattroshapes".into();
+    }
+    json
+  }
+
+  #[test]
+  fn write_all_geojsons_with_pushdown() -> Result<(), String> {
+    let sqlite = SQLite::new(":memory:", SQLiteOpts::default())?;
+    sqlite.create_tables()?;
+    let features = vec![
+      feature(1, "country", 1, false),
+      feature(2, "region", 1, false),
+      feature(3, "region", 0, false),
+      feature(3, "locality", 1, true),
+    ];
+    for json in &features {
+      sqlite.add(WOFGeoJSON::as_valid_wof_geojson(json)?)?;
+    }
+
+    for expression in [
+      "wof:placetype = 'region'",
+      "wof:placetype != 'region' and $id >= 2",
+      "$placetype = 'region' and $is_current",
+      "not $is_current",
+      "wof:hierarchy[0].country_id = 85633147 and wof:population in (10, 30)",
+      "wof:population > 15 and wof:name like '%3'",
+      "wof:placetype = null or wof:placetype = true",
+    ] {
+      let predicate = Predicate::try_from(expression.to_string())?;
+      let mut expected: Vec<u8> = vec![];
+      for json in &features {
+        if json.eval(&predicate)? == Predicate::Boolean(true) {
+          crate::ser::json_to_writer(json, &mut expected).unwrap();
+          writeln!(&mut expected).unwrap();
+        }
+      }
+      let mut output: Vec<u8> = vec![];
+      sqlite.write_all_geojsons(&mut output, &predicate)?;
+      assert_eq!(
+        String::from_utf8(output).unwrap(),
+        String::from_utf8(expected).unwrap(),
+        "{}",
+        expression
+      );
+    }
+
+    let (sql, filter) = sqlite.geojsons_query(&Predicate::try_from(
+      "$placetype = 'region' and wof:population > 15".to_string(),
+    )?)?;
+    assert_eq!(sql, "SELECT geojson.body FROM geojson LEFT JOIN spr ON geojson.id = spr.id WHERE (geojson.is_alt = 1 OR spr.placetype = ?) AND (IFNULL(json_type(CAST(geojson.body AS TEXT), ?), 'null') IN ('integer', 'real') AND json_extract(CAST(geojson.body AS TEXT), ?) > ?);");
+    assert_eq!(
+      filter.remainder,
+      Predicate::try_from("$placetype = 'region'".to_string())?
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn write_all_geojsons_with_unknown_spr_flags() -> Result<(), String> {
+    let sqlite = SQLite::new(":memory:", SQLiteOpts::default())?;
+    sqlite.create_tables()?;
+    let mut features = vec![
+      feature(1, "country", 1, false),
+      feature(2, "region", 0, false),
+    ];
+    features[1]["properties"]["edtf:cessation"] = "2020-01-01".into();
+    for json in &features {
+      sqlite.add(WOFGeoJSON::as_valid_wof_geojson(json)?)?;
+    }
+    // Databases built by other tools store -1 when a flag is unknown.
+    sqlite
+      .conn
+      .execute(
+        "UPDATE spr SET is_current = -1, is_ceased = -1, is_deprecated = -1",
+        [],
+      )
+      .unwrap();
+
+    for (expression, ids) in [
+      ("$is_current", "1"),
+      ("not $is_current", "2"),
+      ("$is_ceased", "2"),
+      ("not $is_deprecated", "1,2"),
+    ] {
+      let mut output: Vec<u8> = vec![];
+      sqlite.write_all_geojsons(&mut output, &Predicate::try_from(expression.to_string())?)?;
+      let output = String::from_utf8(output).unwrap();
+      let found: Vec<String> = output
+        .lines()
+        .map(|line| crate::parse_string_to_json(&line.to_string()).unwrap()["id"].to_string())
+        .collect();
+      assert_eq!(found.join(","), ids, "{}", expression);
+    }
+    Ok(())
+  }
+
+  #[test]
+  fn alternate_geometries() -> Result<(), String> {
+    let sqlite = SQLite::new(":memory:", SQLiteOpts::default())?;
+    sqlite.create_tables()?;
+    let main = feature(3, "region", 0, false);
+    let mut alt = feature(3, "locality", 1, true);
+    alt["properties"]["name:fra_x_preferred"] = json::array!["Alt"];
+    alt["properties"]["wof:hierarchy"] = json::array![object! { "region_id" => 1 }];
+    for json in [&main, &alt] {
+      sqlite.add(WOFGeoJSON::as_valid_wof_geojson(json)?)?;
+    }
+    let (placetype, is_current): (String, i64) = sqlite
+      .conn
+      .query_row(
+        "SELECT placetype, is_current FROM spr WHERE id = 3",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+      )
+      .unwrap();
+    assert_eq!((placetype.as_str(), is_current), ("region", 0));
+    let count = |sql: &str| -> i64 { sqlite.conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+    assert_eq!(count("SELECT count(*) FROM names WHERE id = 3"), 0);
+    assert_eq!(
+      count("SELECT count(*) FROM ancestors WHERE id = 3 AND ancestor_id = 85633147"),
+      1
+    );
+    assert_eq!(count("SELECT count(*) FROM ancestors WHERE id = 3"), 1);
+    assert_eq!(count("SELECT count(*) FROM geojson WHERE id = 3"), 2);
+    Ok(())
+  }
+
+  #[test]
+  fn search() -> Result<(), String> {
+    let sqlite = SQLite::new(
+      ":memory:",
+      SQLiteOpts {
+        search: true,
+        ..Default::default()
+      },
+    )?;
+    sqlite.create_tables()?;
+    let mut features = vec![
+      feature(1, "localadmin", 1, false),
+      feature(2, "locality", 1, false),
+      feature(3, "locality", 1, false),
+    ];
+    features[0]["properties"]["wof:name"] = "Arrondissement de Saint-Benoît".into();
+    features[1]["properties"]["wof:name"] = "Saint-Benoît".into();
+    features[2]["properties"]["wof:name"] = "Saint-Denis".into();
+    features[2]["properties"]["name:fra_x_variant"] = json::array!["Saint-Benoît-du-Nord"];
+    for json in &features {
+      sqlite.add(WOFGeoJSON::as_valid_wof_geojson(json)?)?;
+    }
+    // Adding a document twice replaces its names.
+    sqlite.add(WOFGeoJSON::as_valid_wof_geojson(&features[1])?)?;
+
+    let ids = |results: Vec<JsonValue>| -> Vec<i64> {
+      results
+        .iter()
+        .filter_map(|row| row["id"].as_i64())
+        .collect()
+    };
+    assert_eq!(
+      ids(sqlite.search("saint benoit", None, None, 10)?),
+      vec![2, 1, 3]
+    );
+    assert_eq!(
+      ids(sqlite.search("SAINT BEN", Some("locality"), None, 10)?),
+      vec![2, 3]
+    );
+    assert_eq!(ids(sqlite.search("denis", None, None, 10)?), vec![3]);
+    assert_eq!(ids(sqlite.search("\"denis*", None, None, 1)?), vec![3]);
+    assert_eq!(
+      ids(sqlite.search("benoit", None, Some("FR"), 10)?),
+      Vec::<i64>::new()
+    );
+    assert_eq!(
+      ids(sqlite.search(" - ", None, None, 10)?),
+      Vec::<i64>::new()
+    );
+    let results = sqlite.search("benoit", None, None, 1)?;
+    assert_eq!(results[0]["name"], "Saint-Benoît");
+    assert_eq!(results[0]["placetype"], "locality");
+    Ok(())
+  }
+
+  #[test]
+  fn ids_intersecting_bbox() -> Result<(), String> {
+    let sqlite = SQLite::new(
+      ":memory:",
+      SQLiteOpts {
+        rtree: true,
+        alt: false,
+        ..Default::default()
+      },
+    )?;
+    assert!(sqlite.ids_intersecting_bbox(0.0, 0.0, 1.0, 1.0).is_err());
+    sqlite.create_tables()?;
+    let square = |min: f64, max: f64| {
+      json::array![[[min, min], [max, min], [max, max], [min, max], [min, min]]]
+    };
+    let mut features = vec![
+      feature(1, "country", 1, false),
+      feature(2, "region", 1, false),
+      feature(3, "region", 1, true),
+      feature(4, "locality", 1, false),
+    ];
+    features[0]["geometry"] = object! {
+      "type" => "MultiPolygon",
+      "coordinates" => json::array![square(0.0, 1.0), square(10.0, 11.0)],
+    };
+    features[1]["geometry"] = object! { "type" => "Polygon", "coordinates" => square(0.5, 2.0) };
+    features[2]["geometry"] = object! { "type" => "Polygon", "coordinates" => square(5.0, 6.0) };
+    for json in &features {
+      sqlite.add(WOFGeoJSON::as_valid_wof_geojson(json)?)?;
+    }
+    // Adding a document twice replaces its polygons.
+    sqlite.add(WOFGeoJSON::as_valid_wof_geojson(&features[0])?)?;
+
+    assert_eq!(
+      sqlite.ids_intersecting_bbox(0.8, 0.8, 0.9, 0.9)?,
+      vec![1, 2]
+    );
+    assert_eq!(
+      sqlite.ids_intersecting_bbox(10.5, 10.5, 12.0, 12.0)?,
+      vec![1]
+    );
+    assert_eq!(sqlite.ids_intersecting_bbox(1.5, 1.5, 3.0, 3.0)?, vec![2]);
+    assert_eq!(
+      sqlite.ids_intersecting_bbox(5.0, 5.0, 6.0, 6.0)?,
+      Vec::<i64>::new()
+    );
+    let count: i64 = sqlite
+      .conn
+      .query_row("SELECT count(*) FROM rtree", [], |row| row.get(0))
+      .unwrap();
+    assert_eq!(count, 3);
+
+    // Adding an alternate geometry keeps the polygons of the main document.
+    let sqlite = SQLite::new(
+      ":memory:",
+      SQLiteOpts {
+        rtree: true,
+        ..Default::default()
+      },
+    )?;
+    sqlite.create_tables()?;
+    let mut main = feature(3, "region", 1, false);
+    main["geometry"] = features[2]["geometry"].clone();
+    for json in [&main, &features[2], &features[2]] {
+      sqlite.add(WOFGeoJSON::as_valid_wof_geojson(json)?)?;
+    }
+    let count: i64 = sqlite
+      .conn
+      .query_row("SELECT count(*) FROM rtree WHERE wof_id = 3", [], |row| {
+        row.get(0)
+      })
+      .unwrap();
+    assert_eq!(count, 2);
+    Ok(())
+  }
+
+  #[test]
+  fn properties_and_geometries() -> Result<(), String> {
+    let sqlite = SQLite::new(
+      ":memory:",
+      SQLiteOpts {
+        properties: true,
+        geometries: true,
+        ..Default::default()
+      },
+    )?;
+    assert!(sqlite.for_each_properties(|_, _| Ok(())).is_err());
+    sqlite.create_tables()?;
+    let features = vec![
+      feature(1, "country", 1, false),
+      feature(2, "region", 1, false),
+      feature(2, "region", 1, true),
+    ];
+    for json in &features {
+      sqlite.add(WOFGeoJSON::as_valid_wof_geojson(json)?)?;
+    }
+    let mut properties = vec![];
+    sqlite.for_each_properties(|id, json| {
+      properties.push((id, json.clone()));
+      Ok(())
+    })?;
+    assert_eq!(
+      properties,
+      vec![
+        (1, features[0]["properties"].clone()),
+        (2, features[1]["properties"].clone())
+      ]
+    );
+    let geometries: Vec<(i64, String, bool, String)> = sqlite
+      .conn
+      .prepare("SELECT id, type, is_alt, body FROM geometries ORDER BY id, is_alt")
+      .unwrap()
+      .query_map([], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+      })
+      .unwrap()
+      .collect::<Result<_, _>>()
+      .unwrap();
+    assert_eq!(geometries.len(), 3);
+    assert_eq!(geometries[2].0, 2);
+    assert_eq!(geometries[2].1, "Point");
+    assert!(geometries[2].2);
+    assert_eq!(
+      crate::parse_string_to_json(&geometries[0].3)?,
+      features[0]["geometry"]
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn delete() -> Result<(), String> {
+    let sqlite = SQLite::new(
+      ":memory:",
+      SQLiteOpts {
+        search: true,
+        properties: true,
+        ..Default::default()
+      },
+    )?;
+    sqlite.create_tables()?;
+    let mut features = vec![
+      feature(1, "country", 1, false),
+      feature(2, "region", 1, false),
+      feature(2, "region", 1, true),
+    ];
+    for json in features.iter_mut() {
+      json["properties"]["name:fra_x_preferred"] = json::array!["Place"];
+    }
+    for json in &features {
+      sqlite.add(WOFGeoJSON::as_valid_wof_geojson(json)?)?;
+    }
+    // Adding a document twice replaces its names.
+    sqlite.add(WOFGeoJSON::as_valid_wof_geojson(&features[1])?)?;
+    let count = |table: &str, id: i64| -> i64 {
+      sqlite
+        .conn
+        .query_row(
+          &format!("SELECT count(*) FROM {} WHERE id = ?", table),
+          params![id],
+          |row| row.get(0),
+        )
+        .unwrap()
+    };
+    assert_eq!(count("names", 2), 1);
+    assert_eq!(count("geojson", 2), 2);
+
+    sqlite.delete(2)?;
+    for table in [
+      "geojson",
+      "spr",
+      "names",
+      "ancestors",
+      "search",
+      "properties",
+    ] {
+      assert_eq!(count(table, 2), 0, "{}", table);
+    }
+    assert_eq!(count("geojson", 1), 1);
+    assert_eq!(count("names", 1), 1);
+    Ok(())
+  }
+
+  #[test]
+  fn safe_mode_and_copy() -> Result<(), String> {
+    let directory = std::env::temp_dir();
+    let path = directory.join(format!("wof-safe-{}.db", std::process::id()));
+    let copy = directory.join(format!("wof-copy-{}.db", std::process::id()));
+    let sqlite = SQLite::new(&path, SQLiteOpts::default())?;
+    sqlite.create_tables()?;
+    sqlite.set_safe_mode()?;
+    sqlite.begin()?;
+    sqlite.add(WOFGeoJSON::as_valid_wof_geojson(&feature(
+      1, "country", 1, false,
+    ))?)?;
+    sqlite.commit()?;
+    sqlite.copy_to(&copy)?;
+    assert!(SQLite::new(&copy, SQLiteOpts::default())?
+      .get_geojson_by_id(1)?
+      .is_some());
+    sqlite.checkpoint()?;
+    drop(sqlite);
+    for file in [&path, &copy] {
+      std::fs::remove_file(file).unwrap();
+    }
+    Ok(())
+  }
+
+  #[test]
+  fn existing_tables() -> Result<(), String> {
+    let path = std::env::temp_dir().join(format!("wof-existing-{}.db", std::process::id()));
+    let sqlite = SQLite::new(
+      &path,
+      SQLiteOpts {
+        search: true,
+        rtree: true,
+        properties: true,
+        ..Default::default()
+      },
+    )?;
+    sqlite.create_tables()?;
+    let mut json = feature(1, "country", 1, false);
+    json["properties"]["name:fra_x_preferred"] = json::array!["Corse"];
+    json["geometry"] = object! {
+      "type" => "Polygon",
+      "coordinates" => json::array![[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 0.0]]],
+    };
+    sqlite.add(WOFGeoJSON::as_valid_wof_geojson(&json)?)?;
+    drop(sqlite);
+
+    let mut sqlite = SQLite::new(&path, SQLiteOpts::default())?;
+    sqlite.use_existing_tables()?;
+    assert!(sqlite.opts().search && sqlite.opts().rtree && sqlite.opts().properties);
+    assert!(!sqlite.opts().geometries);
+    json["properties"]["name:fra_x_preferred"] = json::array!["Corsica"];
+    json["geometry"]["coordinates"] =
+      json::array![[[5.0, 5.0], [6.0, 5.0], [6.0, 6.0], [5.0, 5.0]]];
+    sqlite.add(WOFGeoJSON::as_valid_wof_geojson(&json)?)?;
+    let text = |sql: &str| -> String { sqlite.conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+    assert!(text("SELECT names FROM search").contains("Corsica"));
+    assert!(text("SELECT body FROM properties").contains("Corsica"));
+    assert_eq!(
+      sqlite.ids_intersecting_bbox(0.2, 0.2, 0.8, 0.8)?,
+      Vec::<i64>::new()
+    );
+    assert_eq!(sqlite.ids_intersecting_bbox(5.2, 5.2, 5.8, 5.8)?, vec![1]);
+    drop(sqlite);
+    std::fs::remove_file(&path).unwrap();
+    Ok(())
+  }
+}
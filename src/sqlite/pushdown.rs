@@ -0,0 +1,373 @@
+//! Compile the part of a [`Predicate`] that SQLite can evaluate into a `WHERE` clause.
+//!
+//! The predicate is split on its top level `AND`. Conditions on properties are compiled with
+//! `json_extract` on the geojson body and have the same meaning as the in-process evaluation,
+//! documents where the evaluation would fail on mismatched types are filtered out. Conditions on
+//! `$` variables backed by the spr table only pre-filter the rows, they are evaluated again on each
+//! document since alternate geometries share the spr row of their main document.
+use crate::expression::{PathItem, Predicate};
+use rusqlite::types::Value;
+
+/// Part of a predicate compiled to SQL.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SQLFilter {
+  /// Conditions to join with `AND`, empty when nothing can be pushed down.
+  pub conditions: Vec<String>,
+  /// Parameters bound to the `?` of the conditions, in order.
+  pub params: Vec<Value>,
+  /// True when a condition reads a column of the spr table.
+  pub uses_spr: bool,
+  /// What must still be evaluated on each document.
+  pub remainder: Predicate,
+}
+
+const BODY: &str = "CAST(geojson.body AS TEXT)";
+
+/// `$` variables stored in the spr table, with their column. The `is_*` flags are left out, other
+/// builds store `-1` when they are unknown or values computed from other properties.
+const SPR_COLUMNS: [(&str, &str); 6] = [
+  ("$placetype", "spr.placetype"),
+  ("$country", "spr.country"),
+  ("$name", "spr.name"),
+  ("$repo", "spr.repo"),
+  ("$parent_id", "spr.parent_id"),
+  ("$lastmodified", "spr.lastmodified"),
+];
+
+/// Compile the predicate, `spr` tells if the spr table can be used.
+pub fn compile(predicate: &Predicate, spr: bool) -> SQLFilter {
+  let mut filter = SQLFilter {
+    conditions: vec![],
+    params: vec![],
+    uses_spr: false,
+    remainder: Predicate::Boolean(true),
+  };
+  let mut remainder: Vec<Predicate> = vec![];
+  for conjunct in conjuncts(predicate) {
+    let mut compiler = Compiler {
+      spr,
+      params: vec![],
+      uses_spr: false,
+    };
+    match compiler.compile(conjunct) {
+      Some((sql, exact)) => {
+        filter.conditions.push(sql);
+        filter.params.append(&mut compiler.params);
+        filter.uses_spr |= compiler.uses_spr;
+        if !exact {
+          remainder.push(conjunct.clone());
+        }
+      }
+      None => remainder.push(conjunct.clone()),
+    }
+  }
+  if let Some(predicate) = remainder
+    .into_iter()
+    .reduce(|left, right| Predicate::And(Box::new(left), Box::new(right)))
+  {
+    filter.remainder = predicate;
+  }
+  filter
+}
+
+fn conjuncts(predicate: &Predicate) -> Vec<&Predicate> {
+  match predicate {
+    Predicate::And(left, right) => {
+      let mut result = conjuncts(left);
+      result.append(&mut conjuncts(right));
+      result
+    }
+    Predicate::Boolean(true) => vec![],
+    predicate => vec![predicate],
+  }
+}
+
+/// Left side of a comparison that can be read by SQLite.
+enum Operand {
+  /// JSON path of a property in the body.
+  Property(String),
+  /// The `geojson.id` column.
+  Id,
+  /// A column of the spr table.
+  Spr(&'static str),
+}
+
+struct Compiler {
+  spr: bool,
+  params: Vec<Value>,
+  uses_spr: bool,
+}
+
+impl Compiler {
+  /// Returns the SQL and true when it is exactly the predicate, false when it is only a pre-filter.
+  fn compile(&mut self, predicate: &Predicate) -> Option<(String, bool)> {
+    match predicate {
+      Predicate::And(left, right) | Predicate::Or(left, right) => {
+        let operator = if let Predicate::And(_, _) = predicate {
+          "AND"
+        } else {
+          "OR"
+        };
+        let (left, left_exact) = self.compile(left)?;
+        let (right, right_exact) = self.compile(right)?;
+        Some((
+          format!("({} {} {})", left, operator, right),
+          left_exact && right_exact,
+        ))
+      }
+      Predicate::Not(inner) => match inner.as_ref() {
+        Predicate::Variable(_) => self.compare(inner, "=", &Predicate::Boolean(false)),
+        inner => match self.compile(inner)? {
+          (sql, true) => Some((format!("NOT {}", sql), true)),
+          _ => None,
+        },
+      },
+      Predicate::Variable(_) => self.compare(predicate, "=", &Predicate::Boolean(true)),
+      Predicate::Eq(left, right) => self.comparison(left, "=", right),
+      Predicate::Neq(left, right) => self.comparison(left, "!=", right),
+      Predicate::Lt(left, right) => self.comparison(left, "<", right),
+      Predicate::Lte(left, right) => self.comparison(left, "<=", right),
+      Predicate::Gt(left, right) => self.comparison(left, ">", right),
+      Predicate::Gte(left, right) => self.comparison(left, ">=", right),
+      Predicate::In(left, list) => {
+        if list.is_empty() {
+          return Some(("0".to_string(), true));
+        }
+        let mut conditions: Vec<String> = vec![];
+        let mut exact = true;
+        for element in list {
+          let (sql, element_exact) = self.compare(left, "=", element)?;
+          conditions.push(sql);
+          exact &= element_exact;
+        }
+        Some((format!("({})", conditions.join(" OR ")), exact))
+      }
+      Predicate::Boolean(b) => Some(((*b as i32).to_string(), true)),
+      _ => None,
+    }
+  }
+
+  /// Comparison where the literal can be on both sides.
+  fn comparison(
+    &mut self,
+    left: &Predicate,
+    operator: &str,
+    right: &Predicate,
+  ) -> Option<(String, bool)> {
+    if is_literal(left) && !is_literal(right) {
+      let operator = match operator {
+        "<" => ">",
+        "<=" => ">=",
+        ">" => "<",
+        ">=" => "<=",
+        operator => operator,
+      };
+      self.compare(right, operator, left)
+    } else {
+      self.compare(left, operator, right)
+    }
+  }
+
+  fn compare(
+    &mut self,
+    left: &Predicate,
+    operator: &str,
+    literal: &Predicate,
+  ) -> Option<(String, bool)> {
+    if !is_literal(literal) {
+      return None;
+    }
+    match self.operand(left)? {
+      Operand::Property(path) => self.compare_property(path, operator, literal),
+      Operand::Id => match literal {
+        Predicate::Number(n) => {
+          self.params.push(Value::Real(*n));
+          Some((format!("geojson.id {} ?", operator), true))
+        }
+        _ => None,
+      },
+      Operand::Spr(column) => {
+        let value = match literal {
+          Predicate::String(s) => Value::Text(s.to_string()),
+          Predicate::Number(n) => Value::Real(*n),
+          Predicate::Boolean(b) => Value::Integer(*b as i64),
+          _ => return None,
+        };
+        self.params.push(value);
+        self.uses_spr = true;
+        Some((
+          format!("(geojson.is_alt = 1 OR {} {} ?)", column, operator),
+          false,
+        ))
+      }
+    }
+  }
+
+  /// The JSON type of the property is checked first so `1` never equals `true` or `'1'`, missing
+  /// properties have the type `null`.
+  fn compare_property(
+    &mut self,
+    path: String,
+    operator: &str,
+    literal: &Predicate,
+  ) -> Option<(String, bool)> {
+    let json_type = format!("IFNULL(json_type({}, ?), 'null')", BODY);
+    let sql = match (operator, literal) {
+      ("=" | "!=", Predicate::Null) => {
+        self.params.push(Value::Text(path));
+        format!("{} {} 'null'", json_type, operator)
+      }
+      ("=" | "!=", Predicate::Boolean(b)) => {
+        self.params.push(Value::Text(path));
+        format!("{} {} '{}'", json_type, operator, b)
+      }
+      (_, Predicate::String(_) | Predicate::Number(_)) => {
+        let (types, value) = match literal {
+          Predicate::String(s) => ("('text')", Value::Text(s.to_string())),
+          Predicate::Number(n) => ("('integer', 'real')", Value::Real(*n)),
+          _ => return None,
+        };
+        self.params.push(Value::Text(path.clone()));
+        self.params.push(Value::Text(path));
+        self.params.push(value);
+        let (operator, negate) = match operator {
+          "!=" => ("=", "NOT "),
+          operator => (operator, ""),
+        };
+        format!(
+          "{}({} IN {} AND json_extract({}, ?) {} ?)",
+          negate, json_type, types, BODY, operator
+        )
+      }
+      // Ordering with null is always false, with booleans it fails.
+      (_, Predicate::Null) => "0".to_string(),
+      _ => return None,
+    };
+    Some((sql, true))
+  }
+
+  fn operand(&mut self, predicate: &Predicate) -> Option<Operand> {
+    match predicate {
+      Predicate::Variable(name) if name == "$id" => Some(Operand::Id),
+      Predicate::Variable(name) if name.starts_with('$') => {
+        if !self.spr {
+          return None;
+        }
+        SPR_COLUMNS
+          .iter()
+          .find(|(variable, _)| variable == name)
+          .map(|(_, column)| Operand::Spr(column))
+      }
+      Predicate::Variable(name) if name == "geom_type" => None,
+      Predicate::Variable(name) => json_path(&[PathItem::Key(name.to_string())]),
+      Predicate::Path(path) => json_path(path),
+      _ => None,
+    }
+  }
+}
+
+fn is_literal(predicate: &Predicate) -> bool {
+  matches!(
+    predicate,
+    Predicate::String(_) | Predicate::Number(_) | Predicate::Boolean(_) | Predicate::Null
+  )
+}
+
+/// JSON path of a property for `json_extract`, such as `$.properties."wof:hierarchy"[0]`.
+fn json_path(path: &[PathItem]) -> Option<Operand> {
+  let mut json_path = "$.properties".to_string();
+  for item in path {
+    match item {
+      PathItem::Key(key) if key.contains('"') => return None,
+      PathItem::Key(key) => json_path.push_str(&format!(".\"{}\"", key)),
+      PathItem::Index(index) => json_path.push_str(&format!("[{}]", index)),
+    }
+  }
+  Some(Operand::Property(json_path))
+}
+
+#[cfg(test)]
+mod test_pushdown {
+  use super::*;
+  use std::convert::TryFrom;
+
+  fn filter(expression: &str, spr: bool) -> SQLFilter {
+    compile(&Predicate::try_from(expression.to_string()).unwrap(), spr)
+  }
+
+  #[test]
+  fn compile_property() {
+    let filter = filter("wof:placetype = 'country'", true);
+    assert_eq!(
+      filter.conditions,
+      vec!["(IFNULL(json_type(CAST(geojson.body AS TEXT), ?), 'null') IN ('text') AND json_extract(CAST(geojson.body AS TEXT), ?) = ?)"]
+    );
+    assert_eq!(
+      filter.params,
+      vec![
+        Value::Text("$.properties.\"wof:placetype\"".to_string()),
+        Value::Text("$.properties.\"wof:placetype\"".to_string()),
+        Value::Text("country".to_string()),
+      ]
+    );
+    assert_eq!(filter.remainder, Predicate::Boolean(true));
+    assert!(!filter.uses_spr);
+  }
+
+  #[test]
+  fn compile_remainder() {
+    let filter = filter(
+      "$id > 1 and wof:hierarchy[0].country_id != 85633147 and wof:name like 'A%' and geom_type = 'Point'",
+      true,
+    );
+    assert_eq!(filter.conditions.len(), 2);
+    assert_eq!(filter.conditions[0], "geojson.id > ?");
+    assert_eq!(
+      filter.params[1],
+      Value::Text("$.properties.\"wof:hierarchy\"[0].\"country_id\"".to_string())
+    );
+    assert_eq!(
+      filter.remainder,
+      Predicate::try_from("wof:name like 'A%' and geom_type = 'Point'".to_string()).unwrap()
+    );
+  }
+
+  #[test]
+  fn compile_spr() {
+    let with_spr = filter("$is_current and $placetype in ('country', 'region')", true);
+    assert_eq!(
+      with_spr.conditions,
+      vec![
+        "((geojson.is_alt = 1 OR spr.placetype = ?) OR (geojson.is_alt = 1 OR spr.placetype = ?))"
+      ]
+    );
+    assert!(with_spr.uses_spr);
+    assert_eq!(
+      with_spr.remainder,
+      Predicate::try_from("$is_current and $placetype in ('country', 'region')".to_string())
+        .unwrap()
+    );
+
+    for flag in ["$is_current", "not $is_ceased", "$is_deprecated = false"] {
+      assert!(filter(flag, true).conditions.is_empty(), "{}", flag);
+    }
+
+    let without_spr = filter("$placetype = 'country'", false);
+    assert!(without_spr.conditions.is_empty());
+    assert!(!without_spr.uses_spr);
+
+    let negated = filter("not ($placetype = 'country')", true);
+    assert!(negated.conditions.is_empty());
+  }
+
+  #[test]
+  fn compile_literals() {
+    assert_eq!(
+      filter("geom:src = null or 1 < wof:population", true).conditions,
+      vec!["(IFNULL(json_type(CAST(geojson.body AS TEXT), ?), 'null') = 'null' OR (IFNULL(json_type(CAST(geojson.body AS TEXT), ?), 'null') IN ('integer', 'real') AND json_extract(CAST(geojson.body AS TEXT), ?) > ?))"]
+    );
+    assert_eq!(filter("wof:name in ()", true).conditions, vec!["0"]);
+    assert!(filter("wof:name = wof:label", true).conditions.is_empty());
+    assert!(filter("wof:is_funky < true", true).conditions.is_empty());
+  }
+}
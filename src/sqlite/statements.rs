@@ -136,6 +136,110 @@ INSERT OR REPLACE INTO concordances (
   ?, ?, ?, ?
 );"#;
 
+pub const TABLE_SEARCH: &str = r#"CREATE VIRTUAL TABLE IF NOT EXISTS search USING fts5(
+  id UNINDEXED,
+  placetype UNINDEXED,
+  country UNINDEXED,
+  name,
+  names,
+  tokenize = 'unicode61 remove_diacritics 2'
+);"#;
+
+// The rowid of the search table is the id of the document.
+pub const DELETE_SEARCH: &str = "DELETE FROM search WHERE rowid = ?;";
+
+pub const DELETE_GEOJSON: &str = "DELETE FROM geojson WHERE id = ?;";
+
+pub const DELETE_SPR: &str = "DELETE FROM spr WHERE id = ?;";
+
+pub const DELETE_NAMES: &str = "DELETE FROM names WHERE id = ?;";
+
+pub const DELETE_ANCESTORS: &str = "DELETE FROM ancestors WHERE id = ?;";
+
+pub const DELETE_CONCORDANCES: &str = "DELETE FROM concordances WHERE id = ?;";
+
+pub const INSERT_SEARCH: &str = r#"
+INSERT OR REPLACE INTO search (rowid, id, placetype, country, name, names) VALUES (?1, ?1, ?2, ?3, ?4, ?5);
+"#;
+
+// The name column weighs more than the other names.
+pub const SELECT_SEARCH: &str = r#"SELECT
+  spr.id, spr.parent_id, spr.name, spr.placetype, spr.country, spr.repo,
+  spr.latitude, spr.longitude, spr.is_current, spr.is_deprecated,
+  bm25(search, 0.0, 0.0, 0.0, 10.0, 1.0) AS rank
+FROM search JOIN spr ON spr.id = search.id
+WHERE search MATCH ?1 AND (?2 IS NULL OR search.placetype = ?2) AND (?3 IS NULL OR search.country = ?3)
+ORDER BY rank
+LIMIT ?4;"#;
+
+pub const TABLE_PROPERTIES: &str = r#"CREATE TABLE IF NOT EXISTS properties (
+  id INTEGER NOT NULL,
+  body TEXT,
+  is_alt BOOLEAN,
+  alt_label TEXT,
+  lastmodified INTEGER
+);"#;
+
+pub const INDEXES_PROPERTIES: &str = r#"CREATE UNIQUE INDEX IF NOT EXISTS properties_by_id ON properties (id, alt_label);
+CREATE INDEX IF NOT EXISTS properties_by_alt ON properties (id, is_alt, alt_label);
+CREATE INDEX IF NOT EXISTS properties_by_lastmod ON properties (lastmodified);"#;
+
+pub const INSERT_PROPERTIES: &str = r#"
+INSERT OR REPLACE INTO properties (id, body, is_alt, alt_label, lastmodified) VALUES (?, ?, ?, ?, ?)
+"#;
+
+pub const DELETE_PROPERTIES: &str = "DELETE FROM properties WHERE id = ?;";
+
+pub const SELECT_ALL_PROPERTIES_WITHOUT_ALT: &str =
+  "SELECT id, body FROM properties WHERE is_alt = 0;";
+
+// Geometries are stored as GeoJSON, spatialite is not required.
+pub const TABLE_GEOMETRIES: &str = r#"CREATE TABLE IF NOT EXISTS geometries (
+  id INTEGER NOT NULL,
+  type TEXT,
+  is_alt TINYINT,
+  alt_label TEXT,
+  body TEXT,
+  lastmodified INTEGER
+);"#;
+
+pub const DELETE_GEOMETRIES: &str = "DELETE FROM geometries WHERE id = ?;";
+
+pub const INDEXES_GEOMETRIES: &str = r#"CREATE UNIQUE INDEX IF NOT EXISTS geometries_by_id ON geometries (id, alt_label);
+CREATE INDEX IF NOT EXISTS geometries_by_lastmod ON geometries (lastmodified);"#;
+
+pub const INSERT_GEOMETRIES: &str = r#"
+INSERT OR REPLACE INTO geometries (id, type, is_alt, alt_label, body, lastmodified) VALUES (?, ?, ?, ?, ?, ?)
+"#;
+
+// Same schema as the rtree table of go-whosonfirst-sqlite-features, one row per polygon.
+pub const TABLE_RTREE: &str = r#"CREATE VIRTUAL TABLE IF NOT EXISTS rtree USING rtree (
+  id,
+  min_x,
+  max_x,
+  min_y,
+  max_y,
+  +wof_id INTEGER,
+  +is_alt TINYINT,
+  +alt_label TEXT,
+  +geometry BLOB,
+  +lastmodified INTEGER
+);"#;
+
+pub const DELETE_RTREE: &str = "DELETE FROM rtree WHERE wof_id = ? AND alt_label = ?;";
+
+pub const DELETE_RTREE_BY_ID: &str = "DELETE FROM rtree WHERE wof_id = ?;";
+
+pub const INSERT_RTREE: &str = r#"
+INSERT INTO rtree (min_x, max_x, min_y, max_y, wof_id, is_alt, alt_label, geometry, lastmodified)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?);
+"#;
+
+pub const SELECT_RTREE_IDS_BY_BBOX: &str = r#"SELECT DISTINCT wof_id FROM rtree
+WHERE min_x <= ?3 AND max_x >= ?1 AND min_y <= ?4 AND max_y >= ?2
+AND (?5 OR is_alt = 0)
+ORDER BY wof_id;"#;
+
 // Tweaks for perf:
 // https://www.sqlite.org/pragma.html
 // https://blog.devart.com/increasing-sqlite-performance.html
@@ -146,6 +250,17 @@ PRAGMA PAGE_SIZE=4096;
 PRAGMA CACHE_SIZE=1000000;
 PRAGMA TEMP_STORE=MEMORY;"#;
 
+pub const PRAGMA_SAFE: &str = r#"PRAGMA JOURNAL_MODE=WAL;
+PRAGMA SYNCHRONOUS=NORMAL;"#;
+
+pub const CHECKPOINT: &str = "PRAGMA wal_checkpoint(TRUNCATE);";
+
+pub const VACUUM_INTO: &str = "VACUUM INTO ?1;";
+
+pub const BEGIN_TRANSACTION: &str = "BEGIN TRANSACTION;";
+
+pub const COMMIT_TRANSACTION: &str = "COMMIT TRANSACTION;";
+
 pub const SELECT_ALL_IDS: &'static str = "SELECT id FROM geojson";
 
 pub const SELECT_ALL_IDS_WITHOUT_ALT: &'static str = "SELECT id FROM geojson WHERE is_alt = false;";
@@ -158,18 +273,18 @@ pub const SELECT_ALL_IDS_WITHOUT_ALT_AND_DEPRECATED: &'static str = r#"SELECT ge
   geojson LEFT JOIN spr ON geojson.id = spr.id
 ) WHERE geojson.is_alt = false AND spr.is_deprecated = false;"#;
 
-pub const SELECT_ALL_GEOJSONS: &'static str = "SELECT body FROM geojson;";
+pub const SELECT_GEOJSONS: &str = "SELECT geojson.body FROM geojson";
 
-pub const SELECT_ALL_GEOJSONS_WITHOUT_ALT: &'static str =
-  "SELECT body FROM geojson WHERE is_alt = false;";
+pub const SELECT_GEOJSONS_WITH_SPR: &str =
+  "SELECT geojson.body FROM geojson LEFT JOIN spr ON geojson.id = spr.id";
 
-pub const SELECT_ALL_GEOJSONS_WITHOUT_DEPRECATED: &'static str = r#"SELECT geojson.body FROM (
-  geojson LEFT JOIN spr ON geojson.id = spr.id
-) WHERE spr.is_deprecated = false;"#;
+pub const SELECT_GEOJSONS_BY_POINT: &str = r#"SELECT geojson.body FROM spr
+JOIN geojson ON geojson.id = spr.id AND geojson.is_alt = 0
+WHERE spr.min_latitude <= ?2 AND spr.max_latitude >= ?2
+AND spr.min_longitude <= ?1 AND spr.max_longitude >= ?1"#;
 
-pub const SELECT_ALL_GEOJSONS_WITHOUT_ALT_AND_DEPRECATED: &'static str = r#"SELECT geojson.body FROM (
-  geojson LEFT JOIN spr ON geojson.id = spr.id
-) WHERE geojson.is_alt = false AND spr.is_deprecated = false;"#;
+pub const SELECT_TABLE_EXISTS: &str =
+  "SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = ?;";
 
 pub const SELECT_GEOJSON_BY_ID: &'static str =
   "SELECT body FROM geojson where id = ? AND is_alt != 1;";
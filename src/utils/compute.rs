@@ -6,6 +6,7 @@ use md5;
 const DEG_TO_RAD: f64 = 0.0174532925199432958;
 const SCALE_FACTOR: f64 = 0.866025403784438707610604524234;
 const TOTAL_SCALE_3410: f64 = 6371228.0;
+const EARTH_RADIUS_KM: f64 = 6371.0088;
 
 pub trait GeoCompute {
   fn compute_area(&self) -> f64;
@@ -14,6 +15,7 @@ pub trait GeoCompute {
   fn compute_md5(&self) -> String;
   fn compute_centroid(&self) -> (f64, f64);
   fn compute_center_of_mass(&self) -> (f64, f64);
+  fn contains_point(&self, point: &[f64]) -> bool;
 
   fn compute_bbox_string(&self) -> String {
     let bbox = self.compute_bbox();
@@ -97,6 +99,26 @@ fn compute_centroid_polygon(polygon: &Polygon) -> (f64, f64) {
   return (x / (len as f64), y / (len as f64));
 }
 
+/// The point must be inside the outer ring and outside of all holes.
+#[inline]
+pub fn polygon_contains_point(polygon: &Polygon, point: &[f64]) -> bool {
+  match polygon.split_first() {
+    Some((outer, holes)) => {
+      outer.contains_point(point) && !holes.iter().any(|hole| hole.contains_point(point))
+    }
+    None => false,
+  }
+}
+
+/// Great-circle distance in kilometers between two `(lon, lat)` coordinates.
+pub fn compute_distance_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+  let (lon1, lat1) = (from.0 * DEG_TO_RAD, from.1 * DEG_TO_RAD);
+  let (lon2, lat2) = (to.0 * DEG_TO_RAD, to.1 * DEG_TO_RAD);
+  let a = ((lat2 - lat1) / 2.).sin().powi(2)
+    + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.).sin().powi(2);
+  2. * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
 pub fn proj_4326_to_3410(coord: &Vec<f64>) -> Vec<f64> {
   let mut lng = coord[0]; // x
   let mut lat = coord[1]; // y
@@ -134,6 +156,10 @@ impl GeoCompute for Vec<f64> {
     (self[0], self[1])
   }
 
+  fn contains_point(&self, point: &[f64]) -> bool {
+    self[0] == point[0] && self[1] == point[1]
+  }
+
   fn compute_md5(&self) -> String {
     let f32_array: Vec<f32> = self.iter().map(|e| *e as f32).collect();
     let digest = md5::compute(JsonValue::from(f32_array).dump());
@@ -199,6 +225,19 @@ impl GeoCompute for Polyline {
     }
   }
 
+  /// The polyline is a closed ring, the point is inside when a ray crosses it an odd number of times.
+  fn contains_point(&self, point: &[f64]) -> bool {
+    let (x, y) = (point[0], point[1]);
+    let mut inside = false;
+    for pts in self.windows(2) {
+      let (a, b) = (&pts[0], &pts[1]);
+      if (a[1] > y) != (b[1] > y) && x < (b[0] - a[0]) * (y - a[1]) / (b[1] - a[1]) + a[0] {
+        inside = !inside;
+      }
+    }
+    inside
+  }
+
   fn compute_md5(&self) -> String {
     let f32_array: Vec<Vec<f32>> = self
       .iter()
@@ -441,6 +480,30 @@ impl GeoCompute for json::object::Object {
     (0., 0.)
   }
 
+  fn contains_point(&self, point: &[f64]) -> bool {
+    let coords = match self.get("coordinates") {
+      Some(c) => c,
+      _ => return false,
+    };
+    match self.get("type").and_then(|v| v.as_str()) {
+      Some("Point") => coords
+        .as_geom_point()
+        .is_some_and(|p| p.contains_point(point)),
+      Some("MultiPoint") => coords
+        .as_geom_multi_point()
+        .is_some_and(|points| points.iter().any(|p| p.contains_point(point))),
+      Some("Polygon") => coords
+        .as_geom_polygon()
+        .is_some_and(|polygon| polygon_contains_point(&polygon, point)),
+      Some("MultiPolygon") => coords.as_geom_multi_polygon().is_some_and(|multi_polygon| {
+        multi_polygon
+          .iter()
+          .any(|polygon| polygon_contains_point(polygon, point))
+      }),
+      _ => false,
+    }
+  }
+
   fn compute_md5(&self) -> String {
     let mut result: Vec<u8> = vec![];
     crate::object_to_writer(self, &mut result).unwrap();
@@ -563,4 +626,46 @@ mod test {
     assert_eq!(obj.compute_center_of_mass(), (101.5, 1.5));
     assert_eq!(obj.compute_md5(), "e965f294d0c0a5fe9e42a51285edbabd");
   }
+
+  #[test]
+  pub fn contains_point() {
+    let json = json::object! {
+      "coordinates" => vec![
+        vec![vec![
+          vec![102.0, 2.0],
+          vec![103.0, 2.0],
+          vec![103.0, 3.0],
+          vec![102.0, 3.0],
+          vec![102.0, 2.0]]
+        ],
+        vec![vec![
+          vec![100.0, 0.0],
+          vec![101.0, 0.0],
+          vec![101.0, 1.0],
+          vec![100.0, 1.0],
+          vec![100.0, 0.0]],
+        vec![vec![100.2, 0.2],
+          vec![100.8, 0.2],
+          vec![100.8, 0.8],
+          vec![100.2, 0.8],
+          vec![100.2, 0.2]]
+        ]
+      ],
+      "type" => "MultiPolygon"
+    };
+    let obj = json.as_object().unwrap();
+
+    assert!(obj.contains_point(&[102.5, 2.5]));
+    assert!(obj.contains_point(&[100.1, 0.1]));
+    assert!(!obj.contains_point(&[100.5, 0.5]));
+    assert!(!obj.contains_point(&[101.5, 1.5]));
+    assert!(vec![-71.0, 41.0].contains_point(&[-71.0, 41.0]));
+  }
+
+  #[test]
+  pub fn distance_km() {
+    assert_eq!(compute_distance_km((8.7, 41.9), (8.7, 41.9)), 0.0);
+    let distance = compute_distance_km((2.3522, 48.8566), (-0.1276, 51.5072));
+    assert!(distance > 343.0 && distance < 344.0);
+  }
 }
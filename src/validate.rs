@@ -0,0 +1,237 @@
+//! Check that WOF documents are consistent before committing them.
+use crate::utils::{id_to_path_folder, id_to_path_geojson, GeoCompute};
+use crate::wof::WOFGeoJSON;
+use crate::JsonValue;
+use std::path::Path;
+
+/// Properties every WOF document must have.
+pub const REQUIRED_PROPERTIES: [&str; 6] = [
+  "wof:id",
+  "wof:name",
+  "wof:placetype",
+  "wof:repo",
+  "wof:lastmodified",
+  "wof:hierarchy",
+];
+
+/// Maximum difference between a stored coordinate of a bbox and the computed one.
+const BBOX_TOLERANCE: f64 = 1e-7;
+
+/// A problem found in a document, `rule` is a short name of the failing check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+  pub rule: &'static str,
+  pub message: String,
+}
+
+impl ValidationError {
+  fn new(rule: &'static str, message: String) -> Self {
+    ValidationError { rule, message }
+  }
+
+  pub fn to_json(&self) -> JsonValue {
+    json::object! {
+      "rule" => self.rule,
+      "message" => self.message.as_str(),
+    }
+  }
+}
+
+/// Run all checks on the document and returns the problems found, the path is only checked when
+/// the document comes from a file.
+pub fn validate(json: &JsonValue, path: Option<&Path>) -> Vec<ValidationError> {
+  let wof_obj = match WOFGeoJSON::as_valid_wof_geojson(json) {
+    Ok(wof_obj) => wof_obj,
+    Err(e) => return vec![ValidationError::new("geojson", e)],
+  };
+  let mut errors: Vec<ValidationError> = vec![];
+  for property in REQUIRED_PROPERTIES {
+    if wof_obj.properties.get(property).is_none() {
+      errors.push(ValidationError::new(
+        "required",
+        format!("Property `{}` is missing", property),
+      ));
+    }
+  }
+  validate_id(json, &wof_obj, &mut errors);
+  if let Some(path) = path {
+    validate_path(&wof_obj, path, &mut errors);
+  }
+  validate_bbox(&wof_obj, &mut errors);
+  validate_geomhash(&wof_obj, &mut errors);
+  errors
+}
+
+fn validate_id(json: &JsonValue, wof_obj: &WOFGeoJSON, errors: &mut Vec<ValidationError>) {
+  let id = json["id"].as_i64();
+  let wof_id = wof_obj.properties.get("wof:id").and_then(|id| id.as_i64());
+  if wof_id.is_some() && id != wof_id {
+    errors.push(ValidationError::new(
+      "id",
+      format!(
+        "Top level `id` {} is not equal to `wof:id` {}",
+        json["id"].dump(),
+        wof_obj.properties["wof:id"].dump()
+      ),
+    ));
+  }
+}
+
+/// The file must be in the folder of its id and named `{id}.geojson` or `{id}-alt-{label}.geojson`.
+fn validate_path(wof_obj: &WOFGeoJSON, path: &Path, errors: &mut Vec<ValidationError>) {
+  let folder = id_to_path_folder(wof_obj.id);
+  let alt_prefix = format!("{}-alt-", wof_obj.id);
+  let is_alt = path
+    .parent()
+    .is_some_and(|parent| parent.ends_with(&folder))
+    && path
+      .file_name()
+      .and_then(|name| name.to_str())
+      .is_some_and(|name| name.starts_with(&alt_prefix) && name.ends_with(".geojson"));
+  if !path.ends_with(id_to_path_geojson(wof_obj.id)) && !is_alt {
+    errors.push(ValidationError::new(
+      "path",
+      format!(
+        "Path `{}` does not match the id {}",
+        path.display(),
+        wof_obj.id
+      ),
+    ));
+  }
+}
+
+fn validate_bbox(wof_obj: &WOFGeoJSON, errors: &mut Vec<ValidationError>) {
+  let computed = wof_obj.geometry.compute_bbox();
+  if !same_bbox(&wof_obj.bbox, &computed) {
+    errors.push(ValidationError::new(
+      "bbox",
+      format!(
+        "`bbox` {:?} is not equal to the geometry bbox {:?}",
+        wof_obj.bbox, computed
+      ),
+    ));
+  }
+  if let Some(geom_bbox) = wof_obj.properties.get("geom:bbox") {
+    let bbox: Option<Vec<f64>> = geom_bbox.as_str().and_then(|bbox| {
+      bbox
+        .split(',')
+        .map(|coord| coord.trim().parse::<f64>().ok())
+        .collect()
+    });
+    match bbox {
+      Some(bbox) if same_bbox(&bbox, &computed) => (),
+      _ => errors.push(ValidationError::new(
+        "geom:bbox",
+        format!(
+          "`geom:bbox` {} is not equal to the geometry bbox {}",
+          geom_bbox.dump(),
+          wof_obj.geometry.compute_bbox_string()
+        ),
+      )),
+    }
+  }
+}
+
+fn same_bbox(bbox: &[f64], other: &[f64]) -> bool {
+  bbox.len() == 4
+    && other.len() == 4
+    && bbox
+      .iter()
+      .zip(other.iter())
+      .all(|(a, b)| (a - b).abs() <= BBOX_TOLERANCE)
+}
+
+fn validate_geomhash(wof_obj: &WOFGeoJSON, errors: &mut Vec<ValidationError>) {
+  if let Some(geomhash) = wof_obj.properties.get("wof:geomhash") {
+    let computed = wof_obj.geometry.compute_md5();
+    if geomhash.as_str() != Some(computed.as_str()) {
+      errors.push(ValidationError::new(
+        "geomhash",
+        format!(
+          "`wof:geomhash` {} is not equal to the geometry hash \"{}\"",
+          geomhash.dump(),
+          computed
+        ),
+      ));
+    }
+  }
+}
+
+#[cfg(test)]
+mod test_validate {
+  use super::*;
+  use json::object;
+  use std::path::PathBuf;
+
+  fn document() -> JsonValue {
+    object! {
+      "id" => 101748927,
+      "type" => "Feature",
+      "properties" => object!{
+        "geom:bbox" => "8.585396,41.873571,8.826011,41.971536",
+        "wof:geomhash" => "05e3ab4dc5d1dcd4a2ccd2c8a3ee2a9f",
+        "wof:id" => 101748927,
+        "wof:name" => "Ajaccio",
+        "wof:placetype" => "localadmin",
+        "wof:repo" => "whosonfirst-data-admin-fr",
+        "wof:lastmodified" => 1566624060,
+        "wof:hierarchy" => vec![object!{ "localadmin_id" => 101748927 }],
+      },
+      "geometry" => object!{
+        "coordinates" => vec![vec![
+          vec![8.585396,41.873571], vec![8.826011,41.873571], vec![8.826011,41.971536], vec![8.585396,41.968222], vec![8.585396,41.873571]
+        ]],
+        "type" => "Polygon"
+      },
+      "bbox" => vec![8.585396, 41.873571, 8.826011, 41.971536],
+    }
+  }
+
+  fn rules(errors: Vec<ValidationError>) -> Vec<&'static str> {
+    errors.iter().map(|error| error.rule).collect()
+  }
+
+  #[test]
+  fn valid_document() {
+    let mut json = document();
+    let geomhash = WOFGeoJSON::as_valid_wof_geojson(&json)
+      .unwrap()
+      .geometry
+      .compute_md5();
+    json["properties"]["wof:geomhash"] = geomhash.into();
+    assert_eq!(validate(&json, None), vec![]);
+    for path in [
+      "data/101/748/927/101748927.geojson",
+      "101/748/927/101748927-alt-quattroshapes.geojson",
+    ] {
+      assert_eq!(validate(&json, Some(&PathBuf::from(path))), vec![]);
+    }
+  }
+
+  #[test]
+  fn invalid_document() {
+    let mut json = document();
+    json["id"] = 1.into();
+    json["properties"].remove("wof:repo");
+    json["properties"]["geom:bbox"] = "8.585396,41.873571,8.826011".into();
+    json["bbox"] = json::array![8.5, 41.873571, 8.826011, 41.971536];
+    assert_eq!(
+      rules(validate(
+        &json,
+        Some(&PathBuf::from("data/101/748/927/101748927.geojson"))
+      )),
+      vec!["required", "id", "path", "bbox", "geom:bbox", "geomhash"]
+    );
+    assert_eq!(
+      rules(validate(
+        &document(),
+        Some(&PathBuf::from("data/101/748/928/101748927.geojson"))
+      )),
+      vec!["path", "geomhash"]
+    );
+    assert_eq!(
+      rules(validate(&object! { "id" => 101748927 }, None)),
+      vec!["geojson"]
+    );
+  }
+}
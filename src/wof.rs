@@ -1,9 +1,13 @@
 use crate::std::StringifyError;
-use crate::utils::JsonUtils;
+use crate::types::{MultiPolygon, Polygon};
+use crate::utils::{GeoJsonUtils, JsonUtils};
 use crate::{object_to_writer, object_to_writer_pretty, JsonObject, JsonValue};
 use regex::Regex;
 use std::io::Write;
 
+/// EDTF values meaning that the date is unknown or not reached yet.
+pub(crate) const EDTF_UNKNOWN: [&str; 4] = ["", "uuuu", "..", "open"];
+
 /// Representation of a WOF GeoJSON, contains all required properties.
 #[derive(Debug, Clone)]
 pub struct WOFGeoJSON<'a> {
@@ -91,7 +95,8 @@ impl<'a> WOFGeoJSON<'a> {
 
   fn is_property_deprecated(&self, prop: &'static str) -> bool {
     match self.properties.get(prop) {
-      Some(JsonValue::String(s)) => s != "uuuu",
+      Some(JsonValue::String(s)) => !EDTF_UNKNOWN.contains(&s.as_str()),
+      Some(JsonValue::Short(s)) => !EDTF_UNKNOWN.contains(&s.as_str()),
       Some(JsonValue::Boolean(b)) => *b,
       Some(JsonValue::Array(a)) => a.len() > 0,
       _ => false,
@@ -125,14 +130,14 @@ impl<'a> WOFGeoJSON<'a> {
   }
 
   pub fn is_alt_geom(&self) -> bool {
-    match self.properties.get("src:alt_label") {
-      Some(JsonValue::String(_)) => true,
-      _ => false,
-    }
+    matches!(
+      self.properties.get("src:alt_label"),
+      Some(JsonValue::String(_)) | Some(JsonValue::Short(_))
+    )
   }
 
   pub fn is_current(&self) -> bool {
-    self.get_as_i32_or_else("lastmodified", -1) != 0
+    self.get_as_i32_or_else("mz:is_current", -1) != 0
   }
 
   pub fn is_deprecated(&self) -> bool {
@@ -140,7 +145,7 @@ impl<'a> WOFGeoJSON<'a> {
   }
 
   pub fn is_ceased(&self) -> bool {
-    self.is_property_deprecated("wof:cessation")
+    self.is_property_deprecated("edtf:cessation")
   }
 
   pub fn is_superseded(&self) -> bool {
@@ -151,6 +156,22 @@ impl<'a> WOFGeoJSON<'a> {
     self.is_property_deprecated("wof:supersedes")
   }
 
+  pub fn get_alt_label(&self) -> String {
+    self.get_as_string_or_else("src:alt_label", "")
+  }
+
+  /// Polygons of a `Polygon` or a `MultiPolygon` geometry, `None` for other geometries.
+  pub fn get_polygons(&self) -> Option<MultiPolygon> {
+    let coordinates = &self.geometry["coordinates"];
+    match self.geometry["type"].as_str() {
+      Some("Polygon") => coordinates
+        .as_geom_polygon()
+        .map(|polygon: Polygon| vec![polygon]),
+      Some("MultiPolygon") => coordinates.as_geom_multi_polygon(),
+      _ => None,
+    }
+  }
+
   pub fn get_source(&self) -> String {
     self.get_as_string_or_else("src:geom", "unknown")
   }
@@ -0,0 +1,56 @@
+#[macro_use]
+extern crate json;
+use json::JsonValue;
+use wof::sqlite::{SQLite, SQLiteOpts};
+use wof::WOFGeoJSON;
+
+fn feature(id: i32, properties: JsonValue) -> JsonValue {
+  let mut json = object! {
+    "type" => "Feature",
+    "properties" => object!{
+      "name:fra_x_preferred" => vec!["Ajaccio"],
+      "wof:id" => id,
+      "wof:lang" => vec!["fre"],
+      "wof:placetype" => "locality",
+    },
+    "geometry" => object!{
+      "coordinates" => vec![8.7, 41.9],
+      "type" => "Point"
+    },
+    "bbox" => vec![8.7, 41.9, 8.7, 41.9],
+    "id" => id,
+  };
+  for (key, value) in properties.entries() {
+    json["properties"][key] = value.clone();
+  }
+  json
+}
+
+#[test]
+fn no_deprecated_skips_documents_not_current() -> Result<(), String> {
+  let sqlite = SQLite::new(
+    ":memory:",
+    SQLiteOpts {
+      deprecated: false,
+      ..Default::default()
+    },
+  )?;
+  sqlite.create_tables()?;
+  let features = [
+    feature(1, object! { "mz:is_current" => 1 }),
+    feature(2, object! { "mz:is_current" => 0 }),
+    feature(3, object! { "mz:is_current" => -1 }),
+    feature(
+      4,
+      object! { "mz:is_current" => 0, "wof:lastmodified" => 1566485135 },
+    ),
+  ];
+  for json in &features {
+    sqlite.add(WOFGeoJSON::as_valid_wof_geojson(json)?)?;
+  }
+  assert!(sqlite.get_geojson_by_id(1)?.is_some());
+  assert!(sqlite.get_geojson_by_id(2)?.is_none());
+  assert!(sqlite.get_geojson_by_id(3)?.is_some());
+  assert!(sqlite.get_geojson_by_id(4)?.is_none());
+  Ok(())
+}
@@ -48,3 +48,41 @@ fn wrong_geojson() {
   assert!(WOFGeoJSON::as_valid_wof_geojson(&object! {}).is_err());
   assert!(WOFGeoJSON::as_valid_wof_geojson(&object! { "id" => 0 }).is_err());
 }
+
+fn with_properties(properties: json::JsonValue) -> json::JsonValue {
+  object! {
+    "id" => 1,
+    "type" => "Feature",
+    "properties" => properties,
+    "geometry" => object!{ "coordinates" => vec![0, 0], "type" => "Point" },
+    "bbox" => vec![0, 0, 0, 0],
+  }
+}
+
+#[test]
+fn deprecated_dates() {
+  // Dates shorter than 30 bytes are stored as `Short` values, they used to be ignored.
+  for (date, deprecated) in [
+    ("2020-01-01", true),
+    ("uuuu", false),
+    ("", false),
+    ("..", false),
+    ("open", false),
+  ] {
+    let json = with_properties(object! { "edtf:deprecated" => date });
+    let wof_obj = WOFGeoJSON::as_valid_wof_geojson(&json).unwrap();
+    assert_eq!(wof_obj.is_deprecated(), deprecated, "{}", date);
+    assert_eq!(wof_obj.is_doc_deprecated(), deprecated, "{}", date);
+  }
+}
+
+#[test]
+fn ceased_dates() {
+  // The cessation date is `edtf:cessation`, `wof:cessation` used to be read instead.
+  let json = with_properties(object! { "edtf:cessation" => "2019-12-31" });
+  assert!(WOFGeoJSON::as_valid_wof_geojson(&json).unwrap().is_ceased());
+  let json = with_properties(object! { "edtf:cessation" => "uuuu" });
+  assert!(!WOFGeoJSON::as_valid_wof_geojson(&json).unwrap().is_ceased());
+  let json = with_properties(object! { "wof:cessation" => "2019-12-31" });
+  assert!(!WOFGeoJSON::as_valid_wof_geojson(&json).unwrap().is_ceased());
+}